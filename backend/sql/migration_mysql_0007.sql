@@ -0,0 +1,11 @@
+CREATE TABLE `api_token` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `name` VARCHAR(64) NOT NULL,
+  `scopes` VARCHAR(1024) NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `last_used_at` BIGINT NULL,
+  `revoked_at` BIGINT NULL,
+  KEY `account_id` (`account_id`),
+  CONSTRAINT `api_token_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,2 @@
+ALTER TABLE `admin` ADD COLUMN `expires_at` BIGINT NULL,
+  ADD COLUMN `reminded_at` BIGINT NULL;
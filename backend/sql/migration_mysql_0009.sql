@@ -0,0 +1,12 @@
+CREATE TABLE `audit_log` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `actor_id` BIGINT NOT NULL,
+  `action` VARCHAR(64) NOT NULL,
+  `target_id` BIGINT NULL,
+  `payload` TEXT NOT NULL,
+  `logged_at` BIGINT NOT NULL,
+  KEY `actor_id` (`actor_id`),
+  KEY `target_id` (`target_id`),
+  KEY `action` (`action`),
+  CONSTRAINT `audit_log_actor` FOREIGN KEY (`actor_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,19 @@
+CREATE TABLE `admin_history` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `character_id` BIGINT NOT NULL,
+  `action` VARCHAR(16) NOT NULL CHECK (
+    `action` in ('grant', 'change', 'revoke', 'expire')
+  ),
+  `role` VARCHAR(64) NOT NULL,
+  `previous_role` VARCHAR(64) NULL,
+  `expires_at` BIGINT NULL,
+  `changed_by_id` BIGINT NULL,
+  `changed_at` BIGINT NOT NULL,
+  KEY `character_id` (`character_id`),
+  CONSTRAINT `admin_history_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `admin_history_changed_by` FOREIGN KEY (`changed_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+-- Seed the history with the grants we already know about
+INSERT INTO admin_history (character_id, action, role, expires_at, changed_by_id, changed_at)
+SELECT character_id, 'grant', role, expires_at, granted_by_id, granted_at FROM admin;
@@ -0,0 +1,12 @@
+CREATE TABLE `impersonation` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `role` VARCHAR(64) NULL,
+  `target_id` BIGINT NULL,
+  `started_at` BIGINT NOT NULL,
+  `expires_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  KEY `account_id` (`account_id`),
+  CONSTRAINT `impersonation_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `impersonation_target` FOREIGN KEY (`target_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
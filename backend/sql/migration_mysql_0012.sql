@@ -0,0 +1,8 @@
+CREATE TABLE `fleet_motd` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `name` VARCHAR(64) NOT NULL,
+  `body` TEXT NOT NULL,
+  `updated_by_id` BIGINT NOT NULL,
+  `updated_at` BIGINT NOT NULL,
+  CONSTRAINT `fleet_motd_updated_by` FOREIGN KEY (`updated_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,2 @@
+ALTER TABLE `fleet` ADD `auto_reassign` TINYINT NOT NULL DEFAULT 0;
+ALTER TABLE `fleet` ADD CONSTRAINT `fleet_chk_2` CHECK ((`auto_reassign` in (0,1)));
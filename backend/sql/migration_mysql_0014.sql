@@ -0,0 +1,2 @@
+ALTER TABLE `fleet_squad` DROP PRIMARY KEY, ADD PRIMARY KEY (`fleet_id`, `category`, `squad_id`);
+ALTER TABLE `fleet_squad` ADD COLUMN `is_overflow` TINYINT NOT NULL DEFAULT 0;
@@ -0,0 +1,24 @@
+CREATE TABLE `fleet_session` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `fleet_id` BIGINT NOT NULL,
+  `waitlist_id` BIGINT NULL,
+  `started_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  `last_update` BIGINT NOT NULL,
+  `peak_size` INT NOT NULL DEFAULT 0,
+  `member_seconds` BIGINT NOT NULL DEFAULT 0,
+  KEY `fleet_id` (`fleet_id`),
+  KEY `started_at` (`started_at`),
+  CONSTRAINT `fleet_session_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session_boss` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `session_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `started_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  KEY `session_id` (`session_id`),
+  CONSTRAINT `fleet_session_boss_session` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_session_boss_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,6 @@
+CREATE TABLE `job_lease` (
+  `name` VARCHAR(64) NOT NULL PRIMARY KEY,
+  `holder` VARCHAR(64) NOT NULL,
+  `acquired_at` BIGINT NOT NULL,
+  `expires_at` BIGINT NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
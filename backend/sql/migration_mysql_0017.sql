@@ -0,0 +1 @@
+ALTER TABLE `fleet` ADD `last_invite_at` BIGINT NULL;
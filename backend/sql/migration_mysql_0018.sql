@@ -0,0 +1,13 @@
+ALTER TABLE `fit_history` ADD `approved` TINYINT NOT NULL DEFAULT 0;
+ALTER TABLE `fit_history` ADD CONSTRAINT `fit_history_chk_1` CHECK ((`approved` in (0,1)));
+
+CREATE TABLE `fleet_flag` (
+  `fleet_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `flag` VARCHAR(16) NOT NULL,
+  `hull` INT NOT NULL,
+  `flagged_at` BIGINT NOT NULL,
+  PRIMARY KEY (`fleet_id`, `character_id`),
+  CONSTRAINT `fleet_flag_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `fleet_flag_flag` CHECK (`flag` in ('no_xup', 'unapproved', 'banned'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
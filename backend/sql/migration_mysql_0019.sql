@@ -0,0 +1,12 @@
+CREATE TABLE `event_outbox` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `topic` VARCHAR(255) NOT NULL,
+  `event` VARCHAR(64) NOT NULL,
+  `data` MEDIUMTEXT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `delivered_at` BIGINT NULL,
+  `attempts` INT NOT NULL DEFAULT 0,
+  `next_attempt_at` BIGINT NOT NULL,
+  KEY `delivered_at` (`delivered_at`),
+  KEY `created_at` (`created_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,2 @@
+ALTER TABLE `waitlist` ADD `version` BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE `waitlist_entry_fit` ADD `invited_at` BIGINT NULL;
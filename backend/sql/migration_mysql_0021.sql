@@ -0,0 +1,23 @@
+CREATE TABLE `notification` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `kind` VARCHAR(32) NOT NULL,
+  `message` TEXT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `read_at` BIGINT NULL,
+  KEY `account_id` (`account_id`, `id`),
+  CONSTRAINT `notification_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `notification_preference` (
+  `account_id` BIGINT NOT NULL,
+  `kind` VARCHAR(32) NOT NULL,
+  `inbox` TINYINT NOT NULL,
+  `sse` TINYINT NOT NULL,
+  `webhook` TINYINT NOT NULL,
+  PRIMARY KEY (`account_id`, `kind`),
+  CONSTRAINT `notification_preference_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `notification_preference_chk_1` CHECK (`inbox` in (0,1)),
+  CONSTRAINT `notification_preference_chk_2` CHECK (`sse` in (0,1)),
+  CONSTRAINT `notification_preference_chk_3` CHECK (`webhook` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,30 @@
+CREATE TABLE `webhook` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `name` VARCHAR(64) NOT NULL,
+  `url` VARCHAR(512) NOT NULL,
+  `secret` VARCHAR(255) NULL,
+  `events` VARCHAR(255) NOT NULL,
+  `template` TEXT NULL,
+  `enabled` TINYINT NOT NULL,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  CONSTRAINT `webhook_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `webhook_chk_1` CHECK (`enabled` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `webhook_delivery` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `webhook_id` BIGINT NOT NULL,
+  `event` VARCHAR(64) NOT NULL,
+  `payload` MEDIUMTEXT NOT NULL,
+  `status` VARCHAR(16) NOT NULL,
+  `attempts` INT NOT NULL DEFAULT 0,
+  `next_attempt_at` BIGINT NOT NULL,
+  `last_error` TEXT NULL,
+  `created_at` BIGINT NOT NULL,
+  `delivered_at` BIGINT NULL,
+  KEY `status` (`status`, `next_attempt_at`),
+  KEY `webhook_id` (`webhook_id`, `id`),
+  CONSTRAINT `webhook_delivery_webhook` FOREIGN KEY (`webhook_id`) REFERENCES `webhook` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `webhook_delivery_status` CHECK (`status` in ('pending', 'delivered', 'failed'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
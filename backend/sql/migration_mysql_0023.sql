@@ -0,0 +1,17 @@
+CREATE TABLE `waitlist_schedule` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `waitlist_id` BIGINT NOT NULL,
+  `opens_at` BIGINT NOT NULL,
+  `closes_at` BIGINT NOT NULL,
+  `repeat_every` BIGINT NULL,
+  `empty_on_close` TINYINT NOT NULL,
+  `is_opened` TINYINT NOT NULL DEFAULT 0,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  KEY `opens_at` (`opens_at`),
+  KEY `closes_at` (`closes_at`),
+  CONSTRAINT `waitlist_schedule_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `waitlist_schedule_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `waitlist_schedule_chk_1` CHECK (`empty_on_close` in (0,1)),
+  CONSTRAINT `waitlist_schedule_chk_2` CHECK (`is_opened` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,27 @@
+CREATE TABLE `planned_fleet` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `title` VARCHAR(255) NOT NULL,
+  `waitlist_id` BIGINT NOT NULL,
+  `doctrine` VARCHAR(64) NOT NULL,
+  `description` TEXT NULL,
+  `starts_at` BIGINT NOT NULL,
+  `ends_at` BIGINT NOT NULL,
+  `schedule_id` BIGINT NULL,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  KEY `starts_at` (`starts_at`),
+  CONSTRAINT `planned_fleet_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `planned_fleet_schedule` FOREIGN KEY (`schedule_id`) REFERENCES `waitlist_schedule` (`id`) ON DELETE SET NULL,
+  CONSTRAINT `planned_fleet_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `planned_fleet_signup` (
+  `planned_fleet_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `role` VARCHAR(16) NOT NULL,
+  `signed_up_at` BIGINT NOT NULL,
+  PRIMARY KEY (`planned_fleet_id`, `character_id`),
+  CONSTRAINT `planned_fleet_signup_fleet` FOREIGN KEY (`planned_fleet_id`) REFERENCES `planned_fleet` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `planned_fleet_signup_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `planned_fleet_signup_role` CHECK (`role` in ('fc', 'backup'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,24 @@
+ALTER TABLE `announcement`
+  ADD COLUMN `starts_at` BIGINT NULL AFTER `pages`,
+  ADD COLUMN `ends_at` BIGINT NULL AFTER `starts_at`,
+  ADD COLUMN `published_at` BIGINT NULL AFTER `ends_at`,
+  ADD COLUMN `expired_at` BIGINT NULL AFTER `published_at`;
+UPDATE `announcement` SET `starts_at`=`created_at`, `published_at`=`created_at`;
+ALTER TABLE `announcement` MODIFY `starts_at` BIGINT NOT NULL;
+
+CREATE TABLE `announcement_target` (
+  `announcement_id` BIGINT NOT NULL,
+  `kind` VARCHAR(16) NOT NULL,
+  `value` VARCHAR(64) NOT NULL,
+  PRIMARY KEY (`announcement_id`, `kind`, `value`),
+  CONSTRAINT `announcement_target_announcement` FOREIGN KEY (`announcement_id`) REFERENCES `announcement` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `announcement_target_kind` CHECK (`kind` in ('page', 'role', 'waitlist'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+INSERT INTO `announcement_target` (`announcement_id`, `kind`, `value`)
+  SELECT DISTINCT `announcement`.`id`, 'page', `pages`.`page`
+  FROM `announcement`,
+    JSON_TABLE(`announcement`.`pages`, '$[*]' COLUMNS (`page` VARCHAR(64) PATH '$')) AS `pages`
+  WHERE `announcement`.`pages` IS NOT NULL AND JSON_VALID(`announcement`.`pages`);
+
+ALTER TABLE `announcement` DROP COLUMN `pages`;
@@ -0,0 +1,12 @@
+CREATE TABLE `waitlist_invite` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `waitlist_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `fleet_id` BIGINT NOT NULL,
+  `category` VARCHAR(10) NOT NULL,
+  `xup_at` BIGINT NOT NULL,
+  `invited_at` BIGINT NOT NULL,
+  KEY `invited_at` (`invited_at`),
+  CONSTRAINT `waitlist_invite_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `waitlist_invite_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,6 @@
+ALTER TABLE `notification` ADD KEY `created_at` (`created_at`);
+
+-- Webhook delivery of notifications was never implemented
+ALTER TABLE `notification_preference`
+  DROP CHECK `notification_preference_chk_3`,
+  DROP COLUMN `webhook`;
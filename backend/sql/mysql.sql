@@ -42,10 +42,28 @@ CREATE TABLE `admin` (
     `role` VARCHAR(64) NOT NULL,
     `granted_at` BIGINT NOT NULL,
     `granted_by_id` BIGINT NOT NULL,
+    `expires_at` BIGINT NULL,
+    `reminded_at` BIGINT NULL,
     CONSTRAINT `character_role` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
     CONSTRAINT `admin_character` FOREIGN KEY (`granted_by_id`) REFERENCES `character` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `admin_history` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `character_id` BIGINT NOT NULL,
+  `action` VARCHAR(16) NOT NULL CHECK (
+    `action` in ('grant', 'change', 'revoke', 'expire')
+  ),
+  `role` VARCHAR(64) NOT NULL,
+  `previous_role` VARCHAR(64) NULL,
+  `expires_at` BIGINT NULL,
+  `changed_by_id` BIGINT NULL,
+  `changed_at` BIGINT NOT NULL,
+  KEY `character_id` (`character_id`),
+  CONSTRAINT `admin_history_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `admin_history_changed_by` FOREIGN KEY (`changed_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 CREATE TABLE `alt_character` (
   `account_id` bigint NOT NULL,
   `alt_id` bigint NOT NULL,
@@ -55,12 +73,40 @@ CREATE TABLE `alt_character` (
   CONSTRAINT `alt_character_ibfk_2` FOREIGN KEY (`alt_id`) REFERENCES `character` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `api_token` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `name` VARCHAR(64) NOT NULL,
+  `scopes` VARCHAR(1024) NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `last_used_at` BIGINT NULL,
+  `revoked_at` BIGINT NULL,
+  KEY `account_id` (`account_id`),
+  CONSTRAINT `api_token_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `impersonation` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `role` VARCHAR(64) NULL,
+  `target_id` BIGINT NULL,
+  `started_at` BIGINT NOT NULL,
+  `expires_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  KEY `account_id` (`account_id`),
+  CONSTRAINT `impersonation_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `impersonation_target` FOREIGN KEY (`target_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 -- Feature tables
 CREATE TABLE `announcement` (
   `id` BIGINT PRIMARY KEY AUTO_INCREMENT,
   `message` VARCHAR(512) NOT NULL,
   `is_alert` BOOLEAN NOT NULL DEFAULT FALSE,
-  `pages` TEXT,
+  `starts_at` BIGINT NOT NULL,
+  `ends_at` BIGINT NULL,
+  `published_at` BIGINT NULL,
+  `expired_at` BIGINT NULL,
   `created_by_id` BIGINT NOT NULL,
   `created_at` BIGINT NOT NULL,
   `revoked_by_id` BIGINT,
@@ -69,6 +115,15 @@ CREATE TABLE `announcement` (
   CONSTRAINT `announcement_revoked_by` FOREIGN KEY (`revoked_by_id`) REFERENCES `character` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `announcement_target` (
+  `announcement_id` BIGINT NOT NULL,
+  `kind` VARCHAR(16) NOT NULL,
+  `value` VARCHAR(64) NOT NULL,
+  PRIMARY KEY (`announcement_id`, `kind`, `value`),
+  CONSTRAINT `announcement_target_announcement` FOREIGN KEY (`announcement_id`) REFERENCES `announcement` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `announcement_target_kind` CHECK (`kind` in ('page', 'role', 'waitlist'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 CREATE TABLE `ban` (
   `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
   `entity_id` bigint NOT NULL,
@@ -139,13 +194,15 @@ CREATE TABLE `fit_history` (
   `fit_id` bigint NOT NULL,
   `implant_set_id` bigint NOT NULL,
   `logged_at` bigint NOT NULL,
+  `approved` tinyint NOT NULL DEFAULT 0,
   PRIMARY KEY (`id`),
   KEY `character_id` (`character_id`),
   KEY `fit_id` (`fit_id`),
   KEY `implant_set_id` (`implant_set_id`),
   CONSTRAINT `fit_history_ibfk_1` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
   CONSTRAINT `fit_history_ibfk_2` FOREIGN KEY (`fit_id`) REFERENCES `fitting` (`id`),
-  CONSTRAINT `fit_history_ibfk_3` FOREIGN KEY (`implant_set_id`) REFERENCES `implant_set` (`id`)
+  CONSTRAINT `fit_history_ibfk_3` FOREIGN KEY (`implant_set_id`) REFERENCES `implant_set` (`id`),
+  CONSTRAINT `fit_history_chk_1` CHECK ((`approved` in (0,1)))
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 CREATE TABLE `fleet_activity` (
@@ -196,16 +253,115 @@ CREATE TABLE `character_note` (
   CONSTRAINT `character_note_ibfk_2` FOREIGN KEY (`author_id`) REFERENCES `character` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `audit_log` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `actor_id` BIGINT NOT NULL,
+  `action` VARCHAR(64) NOT NULL,
+  `target_id` BIGINT NULL,
+  `payload` TEXT NOT NULL,
+  `logged_at` BIGINT NOT NULL,
+  KEY `actor_id` (`actor_id`),
+  KEY `target_id` (`target_id`),
+  KEY `action` (`action`),
+  CONSTRAINT `audit_log_actor` FOREIGN KEY (`actor_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_motd` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `name` VARCHAR(64) NOT NULL,
+  `body` TEXT NOT NULL,
+  `updated_by_id` BIGINT NOT NULL,
+  `updated_at` BIGINT NOT NULL,
+  CONSTRAINT `fleet_motd_updated_by` FOREIGN KEY (`updated_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `notification` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `account_id` BIGINT NOT NULL,
+  `kind` VARCHAR(32) NOT NULL,
+  `message` TEXT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `read_at` BIGINT NULL,
+  KEY `account_id` (`account_id`, `id`),
+  KEY `created_at` (`created_at`),
+  CONSTRAINT `notification_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `notification_preference` (
+  `account_id` BIGINT NOT NULL,
+  `kind` VARCHAR(32) NOT NULL,
+  `inbox` TINYINT NOT NULL,
+  `sse` TINYINT NOT NULL,
+  PRIMARY KEY (`account_id`, `kind`),
+  CONSTRAINT `notification_preference_account` FOREIGN KEY (`account_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `notification_preference_chk_1` CHECK (`inbox` in (0,1)),
+  CONSTRAINT `notification_preference_chk_2` CHECK (`sse` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `webhook` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `name` VARCHAR(64) NOT NULL,
+  `url` VARCHAR(512) NOT NULL,
+  `secret` VARCHAR(255) NULL,
+  `events` VARCHAR(255) NOT NULL,
+  `template` TEXT NULL,
+  `enabled` TINYINT NOT NULL,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  CONSTRAINT `webhook_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `webhook_chk_1` CHECK (`enabled` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `webhook_delivery` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `webhook_id` BIGINT NOT NULL,
+  `event` VARCHAR(64) NOT NULL,
+  `payload` MEDIUMTEXT NOT NULL,
+  `status` VARCHAR(16) NOT NULL,
+  `attempts` INT NOT NULL DEFAULT 0,
+  `next_attempt_at` BIGINT NOT NULL,
+  `last_error` TEXT NULL,
+  `created_at` BIGINT NOT NULL,
+  `delivered_at` BIGINT NULL,
+  KEY `status` (`status`, `next_attempt_at`),
+  KEY `webhook_id` (`webhook_id`, `id`),
+  CONSTRAINT `webhook_delivery_webhook` FOREIGN KEY (`webhook_id`) REFERENCES `webhook` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `webhook_delivery_status` CHECK (`status` in ('pending', 'delivered', 'failed'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 -- Temporary things
 
+CREATE TABLE `job_lease` (
+  `name` VARCHAR(64) NOT NULL PRIMARY KEY,
+  `holder` VARCHAR(64) NOT NULL,
+  `acquired_at` BIGINT NOT NULL,
+  `expires_at` BIGINT NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `event_outbox` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `topic` VARCHAR(255) NOT NULL,
+  `event` VARCHAR(64) NOT NULL,
+  `data` MEDIUMTEXT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  `delivered_at` BIGINT NULL,
+  `attempts` INT NOT NULL DEFAULT 0,
+  `next_attempt_at` BIGINT NOT NULL,
+  KEY `delivered_at` (`delivered_at`),
+  KEY `created_at` (`created_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 CREATE TABLE `fleet` (
   `id` bigint NOT NULL,
   `boss_id` bigint NOT NULL,
   `is_updating` tinyint DEFAULT NULL,
+  `auto_reassign` tinyint NOT NULL DEFAULT 0,
+  `last_invite_at` bigint DEFAULT NULL,
   PRIMARY KEY (`id`),
   KEY `boss_id` (`boss_id`),
   CONSTRAINT `fleet_ibfk_1` FOREIGN KEY (`boss_id`) REFERENCES `character` (`id`),
-  CONSTRAINT `fleet_chk_1` CHECK ((`is_updating` in (0,1)))
+  CONSTRAINT `fleet_chk_1` CHECK ((`is_updating` in (0,1))),
+  CONSTRAINT `fleet_chk_2` CHECK ((`auto_reassign` in (0,1)))
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 CREATE TABLE `fleet_squad` (
@@ -213,15 +369,28 @@ CREATE TABLE `fleet_squad` (
   `category` varchar(10) NOT NULL,
   `wing_id` bigint NOT NULL,
   `squad_id` bigint NOT NULL,
-  PRIMARY KEY (`fleet_id`,`category`),
+  `is_overflow` tinyint NOT NULL DEFAULT 0,
+  PRIMARY KEY (`fleet_id`,`category`,`squad_id`),
   CONSTRAINT `fleet_squad_ibfk_1` FOREIGN KEY (`fleet_id`) REFERENCES `fleet` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `fleet_flag` (
+  `fleet_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `flag` VARCHAR(16) NOT NULL,
+  `hull` INT NOT NULL,
+  `flagged_at` BIGINT NOT NULL,
+  PRIMARY KEY (`fleet_id`, `character_id`),
+  CONSTRAINT `fleet_flag_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `fleet_flag_flag` CHECK (`flag` in ('no_xup', 'unapproved', 'banned'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 CREATE TABLE `waitlist` (
   `id` bigint NOT NULL AUTO_INCREMENT,
   `name` varchar(255) NOT NULL,
   `is_open` tinyint NOT NULL,
   `is_archived` tinyint NOT NULL,
+  `version` bigint NOT NULL DEFAULT 0,
   PRIMARY KEY (`id`),
   CONSTRAINT `waitlist_chk_1` CHECK ((`is_open` in (0,1))),
   CONSTRAINT `waitlist_chk_2` CHECK ((`is_archived` in (0,1)))
@@ -255,6 +424,7 @@ CREATE TABLE `waitlist_entry_fit` (
   `review_comment` text,
   `cached_time_in_fleet` bigint NOT NULL,
   `is_alt` tinyint NOT NULL,
+  `invited_at` bigint DEFAULT NULL,
   PRIMARY KEY (`id`),
   KEY `character_id` (`character_id`),
   KEY `entry_id` (`entry_id`),
@@ -265,4 +435,88 @@ CREATE TABLE `waitlist_entry_fit` (
   CONSTRAINT `waitlist_entry_fit_ibfk_3` FOREIGN KEY (`fit_id`) REFERENCES `fitting` (`id`),
   CONSTRAINT `waitlist_entry_fit_ibfk_4` FOREIGN KEY (`implant_set_id`) REFERENCES `implant_set` (`id`),
   CONSTRAINT `waitlist_entry_fit_chk_1` CHECK ((`approved` in (0,1)))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `fleet_id` BIGINT NOT NULL,
+  `waitlist_id` BIGINT NULL,
+  `started_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  `last_update` BIGINT NOT NULL,
+  `peak_size` INT NOT NULL DEFAULT 0,
+  `member_seconds` BIGINT NOT NULL DEFAULT 0,
+  KEY `fleet_id` (`fleet_id`),
+  KEY `started_at` (`started_at`),
+  CONSTRAINT `fleet_session_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session_boss` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `session_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `started_at` BIGINT NOT NULL,
+  `ended_at` BIGINT NULL,
+  KEY `session_id` (`session_id`),
+  CONSTRAINT `fleet_session_boss_session` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_session_boss_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `waitlist_schedule` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `waitlist_id` BIGINT NOT NULL,
+  `opens_at` BIGINT NOT NULL,
+  `closes_at` BIGINT NOT NULL,
+  `repeat_every` BIGINT NULL,
+  `empty_on_close` TINYINT NOT NULL,
+  `is_opened` TINYINT NOT NULL DEFAULT 0,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  KEY `opens_at` (`opens_at`),
+  KEY `closes_at` (`closes_at`),
+  CONSTRAINT `waitlist_schedule_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `waitlist_schedule_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `waitlist_schedule_chk_1` CHECK (`empty_on_close` in (0,1)),
+  CONSTRAINT `waitlist_schedule_chk_2` CHECK (`is_opened` in (0,1))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `planned_fleet` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `title` VARCHAR(255) NOT NULL,
+  `waitlist_id` BIGINT NOT NULL,
+  `doctrine` VARCHAR(64) NOT NULL,
+  `description` TEXT NULL,
+  `starts_at` BIGINT NOT NULL,
+  `ends_at` BIGINT NOT NULL,
+  `schedule_id` BIGINT NULL,
+  `created_by_id` BIGINT NOT NULL,
+  `created_at` BIGINT NOT NULL,
+  KEY `starts_at` (`starts_at`),
+  CONSTRAINT `planned_fleet_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `planned_fleet_schedule` FOREIGN KEY (`schedule_id`) REFERENCES `waitlist_schedule` (`id`) ON DELETE SET NULL,
+  CONSTRAINT `planned_fleet_created_by` FOREIGN KEY (`created_by_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `planned_fleet_signup` (
+  `planned_fleet_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `role` VARCHAR(16) NOT NULL,
+  `signed_up_at` BIGINT NOT NULL,
+  PRIMARY KEY (`planned_fleet_id`, `character_id`),
+  CONSTRAINT `planned_fleet_signup_fleet` FOREIGN KEY (`planned_fleet_id`) REFERENCES `planned_fleet` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `planned_fleet_signup_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `planned_fleet_signup_role` CHECK (`role` in ('fc', 'backup'))
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `waitlist_invite` (
+  `id` BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `waitlist_id` BIGINT NOT NULL,
+  `character_id` BIGINT NOT NULL,
+  `fleet_id` BIGINT NOT NULL,
+  `category` VARCHAR(10) NOT NULL,
+  `xup_at` BIGINT NOT NULL,
+  `invited_at` BIGINT NOT NULL,
+  KEY `invited_at` (`invited_at`),
+  CONSTRAINT `waitlist_invite_waitlist` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`),
+  CONSTRAINT `waitlist_invite_character` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
\ No newline at end of file
@@ -7,8 +7,10 @@ pub struct Application {
     pub affiliation_service: crate::core::affiliation::AffiliationService,
     pub ban_service: crate::core::ban::BanService,
     pub esi_client: crate::core::esi::ESIClient,
+    pub public_status: crate::core::public_status::StatusService,
     pub sse_client: crate::core::sse::SSEClient,
     pub token_secret: Vec<u8>,
+    pub waitlist_queue: crate::core::waitlist_queue::QueueEstimates,
 }
 
 pub fn new(db: Arc<crate::DB>, config: Config) -> Application {
@@ -27,11 +29,13 @@ pub fn new(db: Arc<crate::DB>, config: Config) -> Application {
             config.esi.client_id.clone(),
             config.esi.client_secret.clone(),
         ),
-        sse_client: crate::core::sse::SSEClient::new(
-            config.sse.url.clone(),
-            &hex::decode(&config.sse.secret).unwrap(),
+        public_status: crate::core::public_status::StatusService::new(
+            db.clone(),
+            config.public_status.clone(),
         ),
+        sse_client: crate::core::sse::SSEClient::new(&config.sse),
         token_secret: hex::decode(&config.app.token_secret).unwrap(),
+        waitlist_queue: crate::core::waitlist_queue::QueueEstimates::new(db.clone()),
         db,
         config,
     }
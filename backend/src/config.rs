@@ -23,6 +23,9 @@ pub struct AppConfig {
 
 #[derive(Deserialize, Clone)]
 pub struct SSEConfig {
+    // Configs from before the embedded server keep using the external one
+    #[serde(default)]
+    pub embedded: bool,
     pub url: String,
     pub secret: String,
 }
@@ -31,6 +34,18 @@ pub struct SSEConfig {
 pub struct FleetUpdaterConfig {
     pub enable: bool,
     pub min_in_fleet: usize,
+    #[serde(default = "default_max_reassign_per_update")]
+    pub max_reassign_per_update: usize,
+    #[serde(default = "default_reassign_cooldown")]
+    pub reassign_cooldown: i64,
+}
+
+fn default_max_reassign_per_update() -> usize {
+    5
+}
+
+fn default_reassign_cooldown() -> i64 {
+    300
 }
 
 #[derive(Deserialize, Clone)]
@@ -39,6 +54,44 @@ pub struct SkillUpdaterConfig {
     pub runtime: f64,
 }
 
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RoleUpdaterConfig {
+    pub enable: bool,
+    pub remind_before: i64,
+}
+
+impl Default for RoleUpdaterConfig {
+    fn default() -> Self {
+        RoleUpdaterConfig {
+            enable: false,
+            remind_before: 3 * 86400,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct PublicStatusConfig {
+    pub enable: bool,
+    pub cache_seconds: u64,
+    pub requests_per_minute: u32,
+    // Header the reverse proxy puts the client address in. Only set this if the proxy overwrites
+    // it, otherwise clients can pick their own address; without it the socket address is used.
+    pub trusted_proxy_header: Option<String>,
+}
+
+impl Default for PublicStatusConfig {
+    fn default() -> Self {
+        PublicStatusConfig {
+            enable: false,
+            cache_seconds: 10,
+            requests_per_minute: 30,
+            trusted_proxy_header: None,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -47,4 +100,8 @@ pub struct Config {
     pub sse: SSEConfig,
     pub fleet_updater: FleetUpdaterConfig,
     pub skill_updater: SkillUpdaterConfig,
+    #[serde(default)]
+    pub role_updater: RoleUpdaterConfig,
+    #[serde(default)]
+    pub public_status: PublicStatusConfig,
 }
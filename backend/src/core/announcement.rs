@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    auth::{get_access_keys, AuthenticatedAccount},
+    lease::Lease,
+    outbox,
+    sse::Event,
+    webhook,
+};
+use crate::util::madness::Madness;
+
+// Page filters the frontend knows how to match, see page-options.js
+pub const PAGES: &[&str] = &["fits", "guides", "skills", "profile", "waitlist"];
+
+// An announcement is shown where every kind of target that is set matches; no targets means
+// everywhere, for everyone.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Targets {
+    #[serde(default)]
+    pub pages: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub waitlists: Vec<i64>,
+}
+
+impl Targets {
+    pub fn validate(&self) -> Result<(), String> {
+        for page in &self.pages {
+            if !PAGES.contains(&page.as_str()) {
+                return Err(format!("Unknown page \"{}\"", page));
+            }
+        }
+        for role in &self.roles {
+            if get_access_keys(role).is_none() {
+                return Err(format!("Unknown role \"{}\"", role));
+            }
+        }
+        Ok(())
+    }
+
+    // Roles are enforced here, so FC-only announcements never reach anyone else. Pages and
+    // waitlists are matched by the frontend, which knows where the viewer is.
+    pub fn visible_to(&self, account: Option<&AuthenticatedAccount>) -> bool {
+        if self.roles.is_empty() {
+            return true;
+        }
+        let account = match account {
+            Some(account) => account,
+            None => return false,
+        };
+        self.roles
+            .iter()
+            .any(|role| get_access_keys(role).map_or(false, |keys| keys.is_subset(&account.access)))
+    }
+}
+
+pub async fn load_targets<'c, E>(db: E) -> Result<HashMap<i64, Targets>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    let mut targets: HashMap<i64, Targets> = HashMap::new();
+    for target in sqlx::query!(
+        "
+            SELECT announcement_id, kind, value FROM announcement_target
+            JOIN announcement ON announcement.id = announcement_id
+            WHERE revoked_at IS NULL
+        "
+    )
+    .fetch_all(db)
+    .await?
+    {
+        let entry = targets.entry(target.announcement_id).or_default();
+        match target.kind.as_str() {
+            "page" => entry.pages.push(target.value),
+            "role" => entry.roles.push(target.value),
+            "waitlist" => {
+                if let Ok(id) = target.value.parse() {
+                    entry.waitlists.push(id)
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(targets)
+}
+
+pub async fn save_targets(
+    tx: &mut crate::DBTX<'_>,
+    announcement_id: i64,
+    targets: &Targets,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM announcement_target WHERE announcement_id=?",
+        announcement_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let rows = targets
+        .pages
+        .iter()
+        .map(|page| ("page", page.clone()))
+        .chain(targets.roles.iter().map(|role| ("role", role.clone())))
+        .chain(
+            targets
+                .waitlists
+                .iter()
+                .map(|id| ("waitlist", id.to_string())),
+        );
+    for (kind, value) in rows {
+        sqlx::query!(
+            "INSERT IGNORE INTO announcement_target (announcement_id, kind, value) VALUES (?, ?, ?)",
+            announcement_id,
+            kind,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct Changed {
+    id: i64,
+}
+
+// Announcements can be limited to roles, so the event only says which one changed and every
+// client fetches the list it is allowed to see.
+pub async fn notify(
+    tx: &mut crate::DBTX<'_>,
+    event: &str,
+    announcement_id: i64,
+) -> Result<(), sqlx::Error> {
+    outbox::enqueue(
+        tx,
+        vec![Event::new_json(
+            "announcments",
+            event,
+            &Changed {
+                id: announcement_id,
+            },
+        )],
+    )
+    .await
+}
+
+pub async fn went_live(
+    tx: &mut crate::DBTX<'_>,
+    announcement_id: i64,
+    created_by_id: i64,
+    message: &str,
+    is_alert: bool,
+) -> Result<(), sqlx::Error> {
+    webhook::emit(
+        tx,
+        "announcement.create",
+        created_by_id,
+        message.to_string(),
+        vec![("is_alert", is_alert.to_string())],
+    )
+    .await?;
+    notify(tx, "announcment;live", announcement_id).await
+}
+
+// Publishes scheduled announcements when they start and tells clients when they expire
+pub struct AnnouncementPublisher {
+    db: Arc<crate::DB>,
+    lease: Lease,
+}
+
+impl AnnouncementPublisher {
+    pub fn new(db: Arc<crate::DB>) -> AnnouncementPublisher {
+        AnnouncementPublisher {
+            db,
+            lease: Lease::new("announcement_publisher", 60),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in announcement publisher: {:#?}", e);
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        self.lease.run(self.get_db(), self.publish()).await
+    }
+
+    async fn publish(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let starting = sqlx::query!(
+            "
+                SELECT id, message, is_alert AS `is_alert!: bool`, created_by_id FROM announcement
+                WHERE revoked_at IS NULL AND published_at IS NULL AND starts_at <= ?
+                    AND (ends_at IS NULL OR ends_at > ?)
+            ",
+            now,
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+        for announcement in starting {
+            let mut tx = self.get_db().begin().await?;
+            sqlx::query!(
+                "UPDATE announcement SET published_at=? WHERE id=?",
+                now,
+                announcement.id
+            )
+            .execute(&mut tx)
+            .await?;
+            went_live(
+                &mut tx,
+                announcement.id,
+                announcement.created_by_id,
+                &announcement.message,
+                announcement.is_alert,
+            )
+            .await?;
+            tx.commit().await?;
+        }
+
+        let expiring = sqlx::query!(
+            "
+                SELECT id FROM announcement
+                WHERE revoked_at IS NULL AND expired_at IS NULL AND ends_at <= ?
+            ",
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+        for announcement in expiring {
+            let mut tx = self.get_db().begin().await?;
+            sqlx::query!(
+                "UPDATE announcement SET expired_at=? WHERE id=?",
+                now,
+                announcement.id
+            )
+            .execute(&mut tx)
+            .await?;
+            notify(&mut tx, "announcment;expired", announcement.id).await?;
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn account(level: &str) -> AuthenticatedAccount {
+        AuthenticatedAccount {
+            id: 1,
+            access: get_access_keys(level)
+                .cloned()
+                .unwrap_or_else(BTreeSet::new),
+            impersonation: None,
+            api_token: None,
+        }
+    }
+
+    #[test]
+    fn test_visible_to() {
+        let everyone = Targets::default();
+        assert!(everyone.visible_to(None));
+        assert!(everyone.visible_to(Some(&account("user"))));
+
+        let fcs = Targets {
+            roles: vec!["fc".to_string()],
+            ..Default::default()
+        };
+        assert!(!fcs.visible_to(None));
+        assert!(!fcs.visible_to(Some(&account("user"))));
+        assert!(!fcs.visible_to(Some(&account("trainee"))));
+        assert!(fcs.visible_to(Some(&account("fc"))));
+        assert!(fcs.visible_to(Some(&account("council"))));
+    }
+
+    #[test]
+    fn test_validate() {
+        let targets = Targets {
+            pages: vec!["waitlist".to_string()],
+            roles: vec!["fc".to_string()],
+            waitlists: vec![1],
+        };
+        assert!(targets.validate().is_ok());
+
+        let bad_page = Targets {
+            pages: vec!["nowhere".to_string()],
+            ..Default::default()
+        };
+        assert!(bad_page.validate().is_err());
+
+        let bad_role = Targets {
+            roles: vec!["overlord".to_string()],
+            ..Default::default()
+        };
+        assert!(bad_role.validate().is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+// Records a privileged action. The target is the character (or other entity) the action was
+// performed on, if any; everything else about the action goes in the JSON payload.
+pub async fn log<'c, E, P>(
+    db: E,
+    actor_id: i64,
+    action: &str,
+    target_id: Option<i64>,
+    payload: &P,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+    P: Serialize + ?Sized,
+{
+    let now = chrono::Utc::now().timestamp();
+    let payload = serde_json::to_string(payload).unwrap();
+
+    sqlx::query!(
+        "INSERT INTO audit_log (actor_id, action, target_id, payload, logged_at) VALUES (?, ?, ?, ?, ?)",
+        actor_id,
+        action,
+        target_id,
+        payload,
+        now
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
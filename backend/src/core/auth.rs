@@ -1,6 +1,6 @@
 use branca::Branca;
 use rocket::{
-    http::{Header, Status},
+    http::{CookieJar, Header, Method, Status},
     request::{FromRequest, Outcome, Request},
     Response,
 };
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
 static COOKIE_NAME: &str = "authToken";
+// How often the last use of an API token is written back
+const TOKEN_USE_RESOLUTION: i64 = 60;
 
 lazy_static::lazy_static! {
     static ref ACCESS_LEVELS: BTreeMap<String, BTreeSet<String>> = build_access_levels();
@@ -15,13 +17,27 @@ lazy_static::lazy_static! {
 
 pub struct AuthenticatedAccount {
     pub id: i64,
-    pub access: &'static std::collections::BTreeSet<String>,
+    pub access: BTreeSet<String>,
+    pub impersonation: Option<Impersonation>,
+    // Set when the request came in with an API token. Tokens are read-only.
+    pub api_token: Option<i64>,
+}
+
+// Set while an admin is viewing the site as another role or account. The session is read-only.
+#[derive(Debug, Serialize)]
+pub struct Impersonation {
+    pub id: i64,
+    pub admin_id: i64,
+    pub role: Option<String>,
+    pub target_id: Option<i64>,
+    pub expires_at: i64,
 }
 
 #[derive(Debug)]
 pub enum AuthenticationError {
     MissingCookie,
     InvalidToken,
+    ReadOnly,
     DatabaseError(sqlx::Error),
 }
 
@@ -37,6 +53,12 @@ struct AuthToken {
     account_id: i64,
 }
 
+#[derive(Serialize, Deserialize)]
+struct ApiToken {
+    version: i32,
+    token_id: i64,
+}
+
 pub struct CookieSetter(pub String, pub bool);
 impl<'r> rocket::response::Responder<'r, 'static> for CookieSetter {
     fn respond_to(self, _: &'r rocket::request::Request<'_>) -> rocket::response::Result<'static> {
@@ -73,6 +95,38 @@ fn decode_token(token: &str, secret: &[u8]) -> Result<AuthToken, AuthenticationE
     Ok(decoded)
 }
 
+// API tokens don't expire by themselves, they are revoked through the database instead
+fn decode_api_token(token: &str, secret: &[u8]) -> Result<ApiToken, AuthenticationError> {
+    let branca = Branca::new(secret).unwrap();
+    let payload = match branca.decode(token, 0) {
+        Err(_) => return Err(AuthenticationError::InvalidToken),
+        Ok(p) => p,
+    };
+
+    let decoded: ApiToken = match rmp_serde::from_read_ref(&payload) {
+        Err(_) => return Err(AuthenticationError::InvalidToken),
+        Ok(d) => d,
+    };
+
+    if decoded.version != 1 || decoded.token_id <= 0 {
+        return Err(AuthenticationError::InvalidToken);
+    }
+
+    Ok(decoded)
+}
+
+pub fn create_api_token(app: &crate::app::Application, token_id: i64) -> String {
+    let mut branca = Branca::new(&app.token_secret).unwrap();
+
+    let token = ApiToken {
+        version: 1,
+        token_id,
+    };
+
+    let payload = rmp_serde::to_vec_named(&token).unwrap();
+    branca.encode(&payload).unwrap()
+}
+
 pub fn create_cookie(app: &crate::app::Application, account_id: i64) -> CookieSetter {
     let mut branca = Branca::new(&app.token_secret).unwrap();
 
@@ -86,6 +140,171 @@ pub fn create_cookie(app: &crate::app::Application, account_id: i64) -> CookieSe
     CookieSetter(encoded, app.config.esi.url.starts_with("https:"))
 }
 
+// Role grants past their expiry are ignored here, even before the role updater removes them
+pub async fn get_role(db: &crate::DB, character_id: i64) -> Result<Option<String>, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    Ok(sqlx::query!(
+        "SELECT role FROM admin WHERE character_id=? AND (expires_at IS NULL OR expires_at > ?)",
+        character_id,
+        now
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|r| r.role))
+}
+
+async fn account_access(
+    db: &crate::DB,
+    account_id: i64,
+) -> Result<Option<&'static BTreeSet<String>>, sqlx::Error> {
+    let access_level = get_role(db, account_id)
+        .await?
+        .unwrap_or_else(|| "user".to_string());
+
+    Ok(ACCESS_LEVELS.get(&access_level))
+}
+
+async fn authenticate_api_token(
+    app: &crate::app::Application,
+    req: &Request<'_>,
+    bearer: &str,
+) -> Outcome<AuthenticatedAccount, AuthenticationError> {
+    let decoded = match decode_api_token(bearer, &app.token_secret) {
+        Ok(d) => d,
+        Err(e) => return Outcome::Failure((Status::Unauthorized, e)),
+    };
+
+    let token = match sqlx::query!(
+        "SELECT account_id, scopes, last_used_at FROM api_token WHERE id=? AND revoked_at IS NULL",
+        decoded.token_id
+    )
+    .fetch_optional(app.get_db())
+    .await
+    {
+        Err(e) => {
+            return Outcome::Failure((
+                Status::InternalServerError,
+                AuthenticationError::DatabaseError(e),
+            ))
+        }
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            return Outcome::Failure((Status::Unauthorized, AuthenticationError::InvalidToken))
+        }
+    };
+
+    let account_keys = match account_access(app.get_db(), token.account_id).await {
+        Err(e) => {
+            return Outcome::Failure((
+                Status::InternalServerError,
+                AuthenticationError::DatabaseError(e),
+            ))
+        }
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return Outcome::Failure((Status::Unauthorized, AuthenticationError::InvalidToken))
+        }
+    };
+
+    // A token never grants more than its owner currently has, so demotions apply immediately
+    let access = split_token_scopes(&token.scopes)
+        .filter(|scope| account_keys.contains(*scope))
+        .map(|scope| scope.to_string())
+        .collect();
+
+    // Only a rough last use is kept, and failing to record it shouldn't fail the request
+    let now = chrono::Utc::now().timestamp();
+    if token
+        .last_used_at
+        .map_or(true, |used_at| now - used_at >= TOKEN_USE_RESOLUTION)
+    {
+        if let Err(e) = sqlx::query!(
+            "UPDATE api_token SET last_used_at=? WHERE id=?",
+            now,
+            decoded.token_id
+        )
+        .execute(app.get_db())
+        .await
+        {
+            warn!("Could not record use of API token {}: {}", decoded.token_id, e);
+        }
+    }
+
+    info!(
+        "API token {} of account {} used for {} {}",
+        decoded.token_id,
+        token.account_id,
+        req.method(),
+        req.uri()
+    );
+
+    let account = AuthenticatedAccount {
+        id: token.account_id,
+        access,
+        impersonation: None,
+        api_token: Some(decoded.token_id),
+    };
+    if !account.can_write(req.method()) {
+        return Outcome::Failure((Status::Forbidden, AuthenticationError::ReadOnly));
+    }
+
+    Outcome::Success(account)
+}
+
+async fn apply_impersonation(
+    db: &crate::DB,
+    account: AuthenticatedAccount,
+) -> Result<AuthenticatedAccount, sqlx::Error> {
+    if !account.access.contains("impersonate") {
+        return Ok(account);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let impersonation = match sqlx::query!(
+        "SELECT id, role, target_id, expires_at FROM impersonation WHERE account_id=? AND ended_at IS NULL AND expires_at > ?",
+        account.id,
+        now
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        Some(i) => i,
+        None => return Ok(account),
+    };
+
+    let (id, access) = match (&impersonation.role, impersonation.target_id) {
+        (_, Some(target_id)) => (target_id, account_access(db, target_id).await?),
+        (Some(role), None) => (account.id, ACCESS_LEVELS.get(role)),
+        (None, None) => (account.id, None),
+    };
+
+    Ok(AuthenticatedAccount {
+        id,
+        access: access.cloned().unwrap_or_default(),
+        impersonation: Some(Impersonation {
+            id: impersonation.id,
+            admin_id: account.id,
+            role: impersonation.role,
+            target_id: impersonation.target_id,
+            expires_at: impersonation.expires_at,
+        }),
+        api_token: None,
+    })
+}
+
+// The account behind the session cookie, ignoring any impersonation. Used to end an
+// impersonation, which is the one write allowed while it is active.
+pub fn session_account_id(cookies: &CookieJar<'_>, secret: &[u8]) -> Option<i64> {
+    cookies
+        .get(COOKIE_NAME)
+        .and_then(|t| decode_token(t.value(), secret).ok())
+        .map(|t| t.account_id)
+}
+
+pub fn split_token_scopes(scopes: &str) -> impl Iterator<Item = &str> {
+    scopes.split(' ').filter(|s| !s.is_empty())
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for AuthenticatedAccount {
     type Error = AuthenticationError;
@@ -96,6 +315,14 @@ impl<'r> FromRequest<'r> for AuthenticatedAccount {
             .await
             .unwrap();
 
+        if let Some(bearer) = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|h| h.strip_prefix("Bearer "))
+        {
+            return authenticate_api_token(app, req, bearer).await;
+        }
+
         let token = match req.cookies().get(COOKIE_NAME) {
             None => {
                 return Outcome::Failure((Status::Unauthorized, AuthenticationError::MissingCookie))
@@ -106,36 +333,53 @@ impl<'r> FromRequest<'r> for AuthenticatedAccount {
             },
         };
 
-        let access_level =
-            match sqlx::query!("SELECT * FROM admin WHERE character_id=?", token.account_id)
-                .fetch_optional(app.get_db())
-                .await
-            {
-                Err(e) => {
-                    return Outcome::Failure((
-                        Status::InternalServerError,
-                        AuthenticationError::DatabaseError(e),
-                    ))
-                }
-                Ok(Some(r)) => r.role,
-                Ok(None) => "user".to_string(),
-            };
-
-        let access_keys = match ACCESS_LEVELS.get(&access_level) {
-            Some(l) => l,
-            None => {
+        let access_keys = match account_access(app.get_db(), token.account_id).await {
+            Err(e) => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    AuthenticationError::DatabaseError(e),
+                ))
+            }
+            Ok(Some(l)) => l,
+            Ok(None) => {
                 return Outcome::Failure((Status::Unauthorized, AuthenticationError::InvalidToken))
             }
         };
 
-        Outcome::Success(AuthenticatedAccount {
-            id: token.account_id,
-            access: access_keys,
-        })
+        let account = match apply_impersonation(
+            app.get_db(),
+            AuthenticatedAccount {
+                id: token.account_id,
+                access: access_keys.clone(),
+                impersonation: None,
+                api_token: None,
+            },
+        )
+        .await
+        {
+            Ok(a) => a,
+            Err(e) => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    AuthenticationError::DatabaseError(e),
+                ))
+            }
+        };
+
+        if !account.can_write(req.method()) {
+            return Outcome::Failure((Status::Forbidden, AuthenticationError::ReadOnly));
+        }
+
+        Outcome::Success(account)
     }
 }
 
 impl AuthenticatedAccount {
+    // Impersonation sessions and API tokens can only read
+    fn can_write(&self, method: Method) -> bool {
+        method == Method::Get || (self.impersonation.is_none() && self.api_token.is_none())
+    }
+
     pub fn require_access(&self, key: &'static str) -> Result<(), AuthorizationError> {
         match self.access.contains(key) {
             true => Ok(()),
@@ -195,6 +439,7 @@ fn build_access_levels() -> BTreeMap<String, BTreeSet<String>> {
             "skill-history-view",
             "waitlist-edit",
             "stats-view",
+            "api-tokens-manage",
             "waitlist-tag:HQ-FC",
             "notes-view",
             "notes-add",
@@ -215,13 +460,17 @@ fn build_access_levels() -> BTreeMap<String, BTreeSet<String>> {
         &mut result,
         "fc-trainer",
         "council",
-        vec!["commanders-manage:fc-trainer"],
+        vec![
+            "audit-view",
+            "commanders-manage:fc-trainer",
+            "webhooks-manage",
+        ],
     );
     build_level(
         &mut result,
         "council",
         "admin",
-        vec!["commanders-manage:council"],
+        vec!["commanders-manage:council", "impersonate"],
     );
 
     result
@@ -231,17 +480,30 @@ pub fn get_access_keys(level: &str) -> Option<&'static BTreeSet<String>> {
     ACCESS_LEVELS.get(level)
 }
 
+pub fn roles_with_access(key: &str) -> Vec<&'static str> {
+    ACCESS_LEVELS
+        .iter()
+        .filter(|(_, keys)| keys.contains(key))
+        .map(|(role, _)| role.as_str())
+        .collect()
+}
+
 pub async fn authorize_character(
     db: &crate::DB,
     account: &AuthenticatedAccount,
     character_id: i64,
     permission_override: Option<&str>,
 ) -> Result<(), AuthorizationError> {
-    if account.id == character_id {
+    if permission_override.is_some() && account.access.contains(permission_override.unwrap()) {
         return Ok(());
     }
 
-    if permission_override.is_some() && account.access.contains(permission_override.unwrap()) {
+    // A token only reaches characters through the scopes it was given, not through ownership
+    if account.api_token.is_some() {
+        return Err(AuthorizationError::AccessDenied);
+    }
+
+    if account.id == character_id {
         return Ok(());
     }
 
@@ -259,3 +521,31 @@ pub async fn authorize_character(
         Ok(Some(_)) => Ok(()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_account(scopes: &[&str]) -> AuthenticatedAccount {
+        AuthenticatedAccount {
+            id: 1,
+            access: scopes.iter().map(|scope| scope.to_string()).collect(),
+            impersonation: None,
+            api_token: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_token_read_only() {
+        let account = token_account(&["stats-view", "waitlist-view"]);
+        assert!(account.can_write(Method::Get));
+        assert!(!account.can_write(Method::Post));
+        assert!(!account.can_write(Method::Delete));
+
+        let session = AuthenticatedAccount {
+            api_token: None,
+            ..token_account(&[])
+        };
+        assert!(session.can_write(Method::Post));
+    }
+}
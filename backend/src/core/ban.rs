@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+use std::iter;
 use std::sync::Arc;
 
 use crate::util::{
@@ -35,6 +37,52 @@ impl BanService {
         }
     }
 
+    // Which of the given characters are banned directly or through their corporation or alliance,
+    // in a single query
+    pub async fn banned_characters(&self, character_ids: &[i64]) -> Result<HashSet<i64>, Madness> {
+        #[derive(sqlx::FromRow)]
+        struct BannedCharacter {
+            id: i64,
+        }
+
+        if character_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let now: i64 = chrono::Utc::now().timestamp();
+        let placeholders = iter::repeat("?")
+            .take(character_ids.len())
+            .collect::<Vec<_>>()
+            .join(",");
+        let query_str = format!(
+            "
+                SELECT `character`.id FROM `character`
+                LEFT JOIN corporation ON corporation.id = `character`.corporation_id
+                WHERE `character`.id IN ({}) AND EXISTS (
+                    SELECT 1 FROM ban
+                    WHERE (revoked_at IS NULL OR revoked_at > ?) AND (
+                        (entity_type = 'Character' AND entity_id = `character`.id)
+                        OR (entity_type = 'Corporation' AND entity_id = `character`.corporation_id)
+                        OR (entity_type = 'Alliance' AND entity_id = corporation.alliance_id)
+                    )
+                )
+            ",
+            placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, BannedCharacter>(&query_str);
+        for &id in character_ids {
+            query = query.bind(id);
+        }
+        Ok(query
+            .bind(now)
+            .fetch_all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|character| character.id)
+            .collect())
+    }
+
     pub async fn corporation_bans(&self, corporation_id: i64) -> Result<Option<Vec<Ban>>, Madness> {
         if let Some(bans) = self.active_bans(corporation_id, "Corporation").await? {
             Ok(Some(bans))
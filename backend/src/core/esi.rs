@@ -261,6 +261,32 @@ impl ESIRawClient {
 
         Ok(response)
     }
+
+    pub async fn put<E: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        input: &E,
+        access_token: &str,
+    ) -> Result<reqwest::Response, ESIError> {
+        let response = self
+            .http
+            .put(url)
+            .bearer_auth(access_token)
+            .json(input)
+            .send()
+            .await?;
+
+        if let Err(err) = response.error_for_status_ref() {
+            let response_body = response.text().await?;
+            let payload: EsiErrorReason = EsiErrorReason::new(response_body);
+            return Err(ESIError::WithMessage(
+                err.status().unwrap().as_u16(),
+                payload.error,
+            ));
+        };
+
+        Ok(response)
+    }
 }
 
 impl ESIClient {
@@ -469,6 +495,36 @@ impl ESIClient {
         self.raw.post::<E>(&url, input, &access_token).await?;
         Ok(())
     }
+
+    pub async fn post_json<E: Serialize + ?Sized, D: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        input: &E,
+        character_id: i64,
+        scope: ESIScope,
+    ) -> Result<D, ESIError> {
+        let access_token = self.access_token(character_id, scope).await?;
+        let url = format!("https://esi.evetech.net{}", path);
+        Ok(self
+            .raw
+            .post::<E>(&url, input, &access_token)
+            .await?
+            .json()
+            .await?)
+    }
+
+    pub async fn put<E: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        input: &E,
+        character_id: i64,
+        scope: ESIScope,
+    ) -> Result<(), ESIError> {
+        let access_token = self.access_token(character_id, scope).await?;
+        let url = format!("https://esi.evetech.net{}", path);
+        self.raw.put::<E>(&url, input, &access_token).await?;
+        Ok(())
+    }
 }
 
 pub mod fleet_members {
@@ -501,6 +557,131 @@ pub mod fleet_members {
     }
 }
 
+pub mod fleet_wings {
+    use crate::core::esi::ESIScope;
+
+    use super::{ESIClient, ESIError};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ESIFleetWing {
+        pub id: i64,
+        pub name: String,
+        pub squads: Vec<ESIFleetSquad>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ESIFleetSquad {
+        pub id: i64,
+        pub name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct CreatedWing {
+        wing_id: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct CreatedSquad {
+        squad_id: i64,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Naming<'a> {
+        name: &'a str,
+    }
+
+    pub async fn get(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+    ) -> Result<Vec<ESIFleetWing>, ESIError> {
+        Ok(client
+            .get(
+                &format!("/v1/fleets/{}/wings", fleet_id),
+                boss_id,
+                ESIScope::Fleets_ReadFleet_v1,
+            )
+            .await?)
+    }
+
+    pub async fn create_wing(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+        name: &str,
+    ) -> Result<i64, ESIError> {
+        let wing: CreatedWing = client
+            .post_json(
+                &format!("/v1/fleets/{}/wings/", fleet_id),
+                &serde_json::json!({}),
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        client
+            .put(
+                &format!("/v1/fleets/{}/wings/{}/", fleet_id, wing.wing_id),
+                &Naming { name },
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        Ok(wing.wing_id)
+    }
+
+    pub async fn create_squad(
+        client: &ESIClient,
+        fleet_id: i64,
+        wing_id: i64,
+        boss_id: i64,
+        name: &str,
+    ) -> Result<i64, ESIError> {
+        let squad: CreatedSquad = client
+            .post_json(
+                &format!("/v1/fleets/{}/wings/{}/squads/", fleet_id, wing_id),
+                &serde_json::json!({}),
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        client
+            .put(
+                &format!("/v1/fleets/{}/squads/{}/", fleet_id, squad.squad_id),
+                &Naming { name },
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        Ok(squad.squad_id)
+    }
+}
+
+pub mod character_fleet {
+    use crate::core::esi::ESIScope;
+
+    use super::{ESIClient, ESIError};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct ESICharacterFleet {
+        pub fleet_id: i64,
+        pub role: String,
+        pub squad_id: i64,
+        pub wing_id: i64,
+    }
+
+    pub async fn get(client: &ESIClient, character_id: i64) -> Result<ESICharacterFleet, ESIError> {
+        Ok(client
+            .get(
+                &format!("/v1/characters/{}/fleet", character_id),
+                character_id,
+                ESIScope::Fleets_ReadFleet_v1,
+            )
+            .await?)
+    }
+}
+
 fn split_scopes(input: &str) -> BTreeSet<String> {
     input
         .split(' ')
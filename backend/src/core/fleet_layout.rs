@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use crate::core::esi::{fleet_members::ESIFleetMember, fleet_wings, ESIClient};
+use crate::util::madness::Madness;
+
+pub const SQUAD_MEMBER_LIMIT: usize = 256;
+pub const WING_SQUAD_LIMIT: usize = 25;
+
+// EVE cuts wing and squad names off at 10 characters, so compare against what it would store
+pub fn layout_name(name: &str) -> String {
+    name.chars().take(10).collect()
+}
+
+// Finds the first squad for the category with room for one more pilot, creating a new squad (and
+// a wing, if the last one is full) when every existing one is at the member limit. Squads are
+// created while holding a lock on the fleet, so concurrent invites don't each add one.
+pub async fn squad_for_category(
+    db: &crate::DB,
+    esi_client: &ESIClient,
+    fleet_id: i64,
+    boss_id: i64,
+    category: &str,
+    members: &[ESIFleetMember],
+) -> Result<(i64, i64), Madness> {
+    let squads = sqlx::query!(
+        "SELECT wing_id, squad_id FROM fleet_squad WHERE fleet_id=? AND category=? ORDER BY is_overflow, squad_id",
+        fleet_id,
+        category
+    )
+    .fetch_all(db)
+    .await?;
+    let last_wing_id = match squads.last() {
+        Some(squad) => squad.wing_id,
+        None => return Err(Madness::BadRequest("Fleet not configured".to_string())),
+    };
+
+    let mut counts = HashMap::new();
+    for member in members {
+        *counts.entry(member.squad_id).or_insert(0) += 1;
+    }
+    if let Some(squad) = squads
+        .iter()
+        .find(|squad| counts.get(&squad.squad_id).copied().unwrap_or(0) < SQUAD_MEMBER_LIMIT)
+    {
+        return Ok((squad.wing_id, squad.squad_id));
+    }
+
+    let mut tx = db.begin().await?;
+    sqlx::query!("SELECT id FROM fleet WHERE id=? FOR UPDATE", fleet_id)
+        .fetch_optional(&mut tx)
+        .await?;
+
+    // Someone else may have created a squad while we waited for the lock. It is still empty as far
+    // as the member list we were given knows.
+    if let Some(squad) = sqlx::query!(
+        "SELECT wing_id, squad_id FROM fleet_squad WHERE fleet_id=? AND category=? ORDER BY is_overflow, squad_id",
+        fleet_id,
+        category
+    )
+    .fetch_all(&mut tx)
+    .await?
+    .into_iter()
+    .find(|squad| !squads.iter().any(|known| known.squad_id == squad.squad_id))
+    {
+        return Ok((squad.wing_id, squad.squad_id));
+    }
+
+    let name = crate::data::categories::categories()
+        .iter()
+        .find(|c| c.id == category)
+        .map(|c| layout_name(&c.name))
+        .unwrap_or_else(|| layout_name(category));
+
+    let wings = fleet_wings::get(esi_client, fleet_id, boss_id).await?;
+    let wing_id = match wings.iter().find(|wing| wing.id == last_wing_id) {
+        Some(wing) if wing.squads.len() < WING_SQUAD_LIMIT => wing.id,
+        _ => fleet_wings::create_wing(esi_client, fleet_id, boss_id, &name).await?,
+    };
+    let squad_id = fleet_wings::create_squad(esi_client, fleet_id, wing_id, boss_id, &name).await?;
+
+    sqlx::query!(
+        "INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category, is_overflow) VALUES (?, ?, ?, ?, 1)",
+        fleet_id,
+        wing_id,
+        squad_id,
+        category
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "All {} squads of fleet {} were full, created squad {}",
+        category, fleet_id, squad_id
+    );
+
+    Ok((wing_id, squad_id))
+}
@@ -1,18 +1,55 @@
 use crate::core::esi::{self, ESIScope};
-use crate::data::character;
+use crate::core::{
+    ban::BanService,
+    fleet_layout,
+    lease::Lease,
+    notification::{self, Kind},
+    outbox,
+    waitlist_state::{self, Change},
+};
+use crate::data::{categories, character};
 use crate::{config::Config, util::madness::Madness};
-use eve_data_core::TypeID;
+use eve_data_core::{Fitting, TypeDB, TypeID};
+use futures::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::sync::{Arc, Mutex};
 
 use super::sse;
 
+const FLEET_CONCURRENCY: usize = 4;
+const BOSS_LOOKUP_CONCURRENCY: usize = 8;
+
+// Poll intervals in seconds. A fleet whose membership doesn't change is polled less and less
+// often, up to the maximum; an invite (or any change) brings it back to a quick poll.
+const MIN_INTERVAL: i64 = 3;
+const DEFAULT_INTERVAL: i64 = 6;
+const MAX_INTERVAL: i64 = 30;
+const ERROR_INTERVAL: i64 = 30;
+const INVITE_WINDOW: i64 = 60;
+
+// Pilots x up before the fleet forms, so x-ups this long before the session started count too
+const XUP_LOOKBACK: i64 = 2 * 60 * 60;
+// The most time credited to a fleet session between two updates, a few poll intervals
+const MAX_SESSION_GAP: i64 = 90;
+
+struct PollState {
+    polled_at: i64,
+    interval: i64,
+    fingerprint: Option<u64>,
+}
+
 pub struct FleetUpdater {
     esi_client: esi::ESIClient,
-    sse_client: sse::SSEClient,
+    ban_service: BanService,
     db: Arc<crate::DB>,
     config: Config,
+    reassigned_at: Mutex<HashMap<i64, i64>>,
+    poll_state: Mutex<HashMap<i64, PollState>>,
+    lease: Lease,
 }
 
 impl FleetUpdater {
@@ -23,12 +60,12 @@ impl FleetUpdater {
                 config.esi.client_id.clone(),
                 config.esi.client_secret.clone(),
             ),
-            sse_client: sse::SSEClient::new(
-                config.sse.url.clone(),
-                &hex::decode(&config.sse.secret).unwrap(),
-            ),
+            ban_service: BanService::new(db.clone()),
             db,
             config,
+            reassigned_at: Mutex::new(HashMap::new()),
+            poll_state: Mutex::new(HashMap::new()),
+            lease: Lease::new("fleet_updater", 60),
         }
     }
 
@@ -41,7 +78,7 @@ impl FleetUpdater {
     async fn run(self) {
         loop {
             let sleep_time = match self.run_once().await {
-                Ok(()) => 6,
+                Ok(()) => 1,
                 Err(e) => {
                     error!("Error in fleet updater: {:#?}", e);
                     30
@@ -57,20 +94,84 @@ impl FleetUpdater {
     }
 
     async fn run_once(&self) -> Result<(), Madness> {
-        // XXX Get global update lock
+        self.lease.run(self.get_db(), self.update_all()).await
+    }
 
-        let fleets = sqlx::query!("SELECT id FROM fleet")
+    async fn update_all(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let fleets = sqlx::query!("SELECT id, last_invite_at FROM fleet")
             .fetch_all(self.get_db())
             .await?;
 
-        for fleet in fleets {
-            self.update_fleet(fleet.id).await?;
+        let due: Vec<i64> = {
+            let mut poll_state = self.poll_state.lock().unwrap();
+            poll_state.retain(|id, _| fleets.iter().any(|fleet| fleet.id == *id));
+
+            fleets
+                .iter()
+                .filter(|fleet| match poll_state.get(&fleet.id) {
+                    Some(state) => {
+                        let since = now - state.polled_at;
+                        let invited = fleet
+                            .last_invite_at
+                            .map_or(false, |invited_at| invited_at >= state.polled_at);
+                        since >= state.interval || (invited && since >= MIN_INTERVAL)
+                    }
+                    None => true,
+                })
+                .map(|fleet| fleet.id)
+                .collect()
+        };
+
+        let results: Vec<(i64, Result<Option<u64>, Madness>)> = stream::iter(due)
+            .map(|fleet_id| async move { (fleet_id, self.update_fleet(fleet_id).await) })
+            .buffer_unordered(FLEET_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut poll_state = self.poll_state.lock().unwrap();
+        for (fleet_id, result) in results {
+            let previous = poll_state.remove(&fleet_id);
+            let (interval, fingerprint) = match result {
+                Err(e) => {
+                    error!("Error updating fleet {}: {:#?}", fleet_id, e);
+                    (ERROR_INTERVAL, previous.and_then(|state| state.fingerprint))
+                }
+                // The fleet is gone or moved to a new boss, start from scratch next time
+                Ok(None) => continue,
+                Ok(Some(fingerprint)) => {
+                    let recently_invited = fleets.iter().any(|fleet| {
+                        fleet.id == fleet_id
+                            && fleet
+                                .last_invite_at
+                                .map_or(false, |invited_at| invited_at > now - INVITE_WINDOW)
+                    });
+                    let interval = match previous {
+                        _ if recently_invited => MIN_INTERVAL,
+                        Some(state) if state.fingerprint == Some(fingerprint) => {
+                            (state.interval.max(DEFAULT_INTERVAL) * 2).min(MAX_INTERVAL)
+                        }
+                        _ => DEFAULT_INTERVAL,
+                    };
+                    (interval, Some(fingerprint))
+                }
+            };
+
+            poll_state.insert(
+                fleet_id,
+                PollState {
+                    polled_at: now,
+                    interval,
+                    fingerprint,
+                },
+            );
         }
 
         Ok(())
     }
 
-    async fn update_fleet(&self, fleet_id: i64) -> Result<(), Madness> {
+    // Returns a fingerprint of the fleet membership, or None if the fleet is no longer ours
+    async fn update_fleet(&self, fleet_id: i64) -> Result<Option<u64>, Madness> {
         let fleet = sqlx::query!("SELECT * FROM fleet WHERE id = ?", fleet_id)
             .fetch_one(self.get_db())
             .await?;
@@ -83,8 +184,19 @@ impl FleetUpdater {
                     | esi::ESIError::NoToken
                     | esi::ESIError::MissingScope,
                 ) => {
+                    // The boss may have passed boss to another FC, in which case we keep the fleet
+                    if let Some(new_boss_id) = self.find_new_boss(fleet_id, fleet.boss_id).await? {
+                        self.hand_over_boss(fleet_id, fleet.boss_id, new_boss_id)
+                            .await?;
+                        return Ok(None);
+                    }
+
                     // 403/404 => Delete the fleet, move on
                     let mut tx = self.get_db().begin().await?;
+                    Self::end_session(&mut tx, fleet_id).await?;
+                    sqlx::query!("DELETE FROM fleet_flag WHERE fleet_id=?", fleet_id)
+                        .execute(&mut tx)
+                        .await?;
                     sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", fleet_id)
                         .execute(&mut tx)
                         .await?;
@@ -92,7 +204,7 @@ impl FleetUpdater {
                         .execute(&mut tx)
                         .await?;
                     tx.commit().await?;
-                    return Ok(());
+                    return Ok(None);
                 }
                 Err(e) => return Err(Madness::from(e)),
             };
@@ -129,44 +241,72 @@ impl FleetUpdater {
             }
         }
 
-        let changed_waitlist_ids: Vec<i64> = {
-            // Update the waitlist: remove people who are in fleet
-            let mut changed = HashSet::new();
-
-            let on_waitlist: HashMap<i64, _> =
-                sqlx::query!("SELECT entry_id, waitlist_id, character_id, is_alt FROM waitlist_entry_fit JOIN waitlist_entry ON waitlist_entry_fit.entry_id=waitlist_entry.id")
-                    .fetch_all(self.get_db())
-                    .await?
-                    .into_iter()
-                    .map(|r| (r.character_id, r))
-                    .collect();
+        {
+            // Update the waitlist: remove people who are in fleet. An alt takes all its x-ups
+            // along, a main the main x-ups of its entry.
+            let on_waitlist = sqlx::query!(
+                "
+                    SELECT wef.id, entry_id, waitlist_id, character_id, is_alt FROM waitlist_entry_fit wef
+                    JOIN waitlist_entry we ON wef.entry_id=we.id
+                "
+            )
+            .fetch_all(self.get_db())
+            .await?;
 
-            let mut tx = self.get_db().begin().await?;
-            for &id in &member_ids {
-                if let Some(record) = on_waitlist.get(&id) {
-                    changed.insert(record.waitlist_id);
-                    if record.is_alt > 0 {
-                        sqlx::query!(
-                            "DELETE FROM waitlist_entry_fit WHERE character_id=?",
-                            record.character_id
-                        )
+            let mut alts = HashSet::new();
+            let mut main_entries = HashSet::new();
+            for fit in &on_waitlist {
+                if members.contains_key(&fit.character_id) {
+                    if fit.is_alt > 0 {
+                        alts.insert(fit.character_id);
+                    } else {
+                        main_entries.insert(fit.entry_id);
+                    }
+                }
+            }
+            let (removed, remaining): (Vec<_>, Vec<_>) = on_waitlist.iter().partition(|fit| {
+                alts.contains(&fit.character_id)
+                    || (fit.is_alt == 0 && main_entries.contains(&fit.entry_id))
+            });
+
+            if !removed.is_empty() {
+                let remaining_entries: HashSet<i64> =
+                    remaining.iter().map(|fit| fit.entry_id).collect();
+                let mut changes: BTreeMap<i64, Vec<Change>> = BTreeMap::new();
+
+                let mut tx = self.get_db().begin().await?;
+                for fit in &removed {
+                    sqlx::query!("DELETE FROM waitlist_entry_fit WHERE id=?", fit.id)
                         .execute(&mut tx)
                         .await?;
-                    } else {
-                        sqlx::query!(
-                            "DELETE FROM waitlist_entry_fit WHERE entry_id=? AND is_alt = 0",
-                            record.entry_id
-                        )
+                    changes
+                        .entry(fit.waitlist_id)
+                        .or_default()
+                        .push(Change::FitRemoved {
+                            entry_id: fit.entry_id,
+                            fit_id: fit.id,
+                        });
+                }
+                let emptied: BTreeSet<(i64, i64)> = removed
+                    .iter()
+                    .filter(|fit| !remaining_entries.contains(&fit.entry_id))
+                    .map(|fit| (fit.waitlist_id, fit.entry_id))
+                    .collect();
+                for (waitlist_id, entry_id) in emptied {
+                    sqlx::query!("DELETE FROM waitlist_entry WHERE id=?", entry_id)
                         .execute(&mut tx)
                         .await?;
-                    }
+                    changes
+                        .entry(waitlist_id)
+                        .or_default()
+                        .push(Change::EntryRemoved { entry_id });
                 }
+                for (waitlist_id, changes) in changes {
+                    waitlist_state::publish(&mut tx, waitlist_id, &changes).await?;
+                }
+                tx.commit().await?;
             }
-            sqlx::query!("DELETE FROM waitlist_entry WHERE id NOT IN (SELECT entry_id FROM waitlist_entry_fit)").execute(&mut tx).await?;
-            tx.commit().await?;
-
-            changed.into_iter().collect()
-        };
+        }
 
         let fleet_comp_changed: bool = {
             // Update the fleet activity data
@@ -246,35 +386,520 @@ impl FleetUpdater {
             new_fleet_comp
         };
 
-        self.notify_sse(fleet_id, &changed_waitlist_ids, fleet_comp_changed)
+        let session_start = self
+            .update_session(fleet_id, fleet.boss_id, members.len())
+            .await?;
+
+        self.flag_members(fleet_id, fleet.boss_id, session_start, &members_raw)
+            .await?;
+
+        if fleet.auto_reassign > 0 {
+            self.reassign_members(fleet_id, fleet.boss_id, &members_raw)
+                .await?;
+        }
+
+        self.notify_sse(fleet_id, fleet_comp_changed).await?;
+
+        let mut membership: Vec<_> = members_raw
+            .iter()
+            .map(|m| (m.character_id, m.ship_type_id, m.squad_id))
+            .collect();
+        membership.sort_unstable();
+        let mut hasher = DefaultHasher::new();
+        membership.hash(&mut hasher);
+
+        Ok(Some(hasher.finish()))
+    }
+
+    async fn update_session(
+        &self,
+        fleet_id: i64,
+        boss_id: i64,
+        size: usize,
+    ) -> Result<i64, Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let size = size as i64;
+
+        let mut tx = self.get_db().begin().await?;
+        let (session_id, started_at) = match sqlx::query!(
+            "SELECT id, started_at, last_update FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL",
+            fleet_id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            Some(session) => {
+                // Accumulated as pilot-seconds, the average size is that divided by the duration. After
+                // the updater was down, the members are only credited up to the longest poll gap.
+                sqlx::query!(
+                    "
+                        UPDATE fleet_session
+                        SET last_update=?, peak_size=GREATEST(peak_size, ?), member_seconds=member_seconds + ?
+                        WHERE id=?
+                    ",
+                    now,
+                    size,
+                    size * (now - session.last_update).clamp(0, MAX_SESSION_GAP),
+                    session.id
+                )
+                .execute(&mut tx)
+                .await?;
+                (session.id, session.started_at)
+            }
+            None => {
+                let result = sqlx::query!(
+                    "INSERT INTO fleet_session (fleet_id, started_at, last_update, peak_size) VALUES (?, ?, ?, ?)",
+                    fleet_id,
+                    now,
+                    now,
+                    size
+                )
+                .execute(&mut tx)
+                .await?;
+                (crate::last_insert_id!(result), now)
+            }
+        };
+
+        let current_boss = sqlx::query!(
+            "SELECT id, character_id FROM fleet_session_boss WHERE session_id=? AND ended_at IS NULL",
+            session_id
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+        if current_boss.as_ref().map(|boss| boss.character_id) != Some(boss_id) {
+            if let Some(previous) = current_boss {
+                sqlx::query!(
+                    "UPDATE fleet_session_boss SET ended_at=? WHERE id=?",
+                    now,
+                    previous.id
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+            sqlx::query!(
+                "INSERT INTO fleet_session_boss (session_id, character_id, started_at) VALUES (?, ?, ?)",
+                session_id,
+                boss_id,
+                now
+            )
+            .execute(&mut tx)
             .await?;
+        }
+        tx.commit().await?;
+
+        Ok(started_at)
+    }
+
+    async fn end_session(tx: &mut crate::DBTX<'_>, fleet_id: i64) -> Result<(), Madness> {
+        // The fleet was last seen alive at the previous update, so that's when it ended
+        sqlx::query!(
+            "
+                UPDATE fleet_session_boss
+                JOIN fleet_session ON fleet_session.id = fleet_session_boss.session_id
+                SET fleet_session_boss.ended_at = fleet_session.last_update
+                WHERE fleet_session.fleet_id=? AND fleet_session.ended_at IS NULL AND fleet_session_boss.ended_at IS NULL
+            ",
+            fleet_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE fleet_session SET ended_at=last_update WHERE fleet_id=? AND ended_at IS NULL",
+            fleet_id
+        )
+        .execute(&mut *tx)
+        .await?;
 
         Ok(())
     }
 
-    async fn notify_sse(
+    async fn flag_members(
         &self,
         fleet_id: i64,
-        changed_waitlist_ids: &[i64],
-        fleet_comp_changed: bool,
+        boss_id: i64,
+        session_start: i64,
+        members: &[esi::fleet_members::ESIFleetMember],
+    ) -> Result<(), Madness> {
+        #[derive(Debug, sqlx::FromRow)]
+        struct XupRecord {
+            character_id: i64,
+            hull: i32,
+            approved: i8,
+        }
+
+        let now = chrono::Utc::now().timestamp();
+
+        // FCs and other role holders hop in and out of fleets without x-ing up
+        let fcs: HashSet<i64> = sqlx::query!(
+            "SELECT character_id FROM admin WHERE expires_at IS NULL OR expires_at > ?",
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?
+        .into_iter()
+        .map(|admin| admin.character_id)
+        .collect();
+        let to_check: Vec<_> = members
+            .iter()
+            .filter(|m| m.character_id != boss_id && !fcs.contains(&m.character_id))
+            .collect();
+
+        let mut xups: HashMap<i64, Vec<(TypeID, bool)>> = HashMap::new();
+        if !to_check.is_empty() {
+            let placeholders = iter::repeat("?")
+                .take(to_check.len())
+                .collect::<Vec<_>>()
+                .join(",");
+            let query_str = format!(
+                "
+                    SELECT character_id, hull, approved FROM fit_history
+                    JOIN fitting ON fitting.id = fit_history.fit_id
+                    WHERE logged_at >= ? AND character_id IN ({})
+                ",
+                placeholders
+            );
+            let mut query =
+                sqlx::query_as::<_, XupRecord>(&query_str).bind(session_start - XUP_LOOKBACK);
+            for member in &to_check {
+                query = query.bind(member.character_id);
+            }
+            for xup in query.fetch_all(self.get_db()).await? {
+                xups.entry(xup.character_id)
+                    .or_default()
+                    .push((xup.hull as TypeID, xup.approved > 0));
+            }
+        }
+
+        // Nobody is exempt from the ban check, not even FCs or the boss
+        let member_ids: Vec<i64> = members.iter().map(|m| m.character_id).collect();
+        let banned = self.ban_service.banned_characters(&member_ids).await?;
+
+        let mut flags = HashMap::new();
+        for member in members {
+            if banned.contains(&member.character_id) {
+                flags.insert(member.character_id, ("banned", member.ship_type_id));
+            }
+        }
+        for member in to_check {
+            if flags.contains_key(&member.character_id) {
+                continue;
+            }
+            let approved_hull = xups.get(&member.character_id).map(|xups| {
+                xups.iter()
+                    .any(|&(hull, approved)| approved && hull == member.ship_type_id)
+            });
+            let flag = match approved_hull {
+                None => "no_xup",
+                Some(false) => "unapproved",
+                Some(true) => continue,
+            };
+            flags.insert(member.character_id, (flag, member.ship_type_id));
+        }
+
+        let existing: HashMap<i64, (String, TypeID)> = sqlx::query!(
+            "SELECT character_id, flag, hull FROM fleet_flag WHERE fleet_id=?",
+            fleet_id
+        )
+        .fetch_all(self.get_db())
+        .await?
+        .into_iter()
+        .map(|flag| (flag.character_id, (flag.flag, flag.hull as TypeID)))
+        .collect();
+
+        let mut raised = Vec::new();
+        let mut tx = self.get_db().begin().await?;
+        for &id in existing.keys() {
+            if !flags.contains_key(&id) {
+                sqlx::query!(
+                    "DELETE FROM fleet_flag WHERE fleet_id=? AND character_id=?",
+                    fleet_id,
+                    id
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+        for (&id, &(flag, hull)) in &flags {
+            if let Some((old_flag, old_hull)) = existing.get(&id) {
+                if old_flag == flag && *old_hull == hull {
+                    continue;
+                }
+            }
+            sqlx::query!(
+                "REPLACE INTO fleet_flag (fleet_id, character_id, flag, hull, flagged_at) VALUES (?, ?, ?, ?, ?)",
+                fleet_id,
+                id,
+                flag,
+                hull,
+                now
+            )
+            .execute(&mut tx)
+            .await?;
+            raised.push((id, flag, hull));
+        }
+
+        let ids: Vec<i64> = raised.iter().map(|&(id, _, _)| id).collect();
+        let names = character::lookup(self.get_db(), &ids).await?;
+        for (id, flag, hull) in raised {
+            let name = names
+                .get(&id)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| id.to_string());
+            let hull_name = TypeDB::name_of(hull).unwrap_or_else(|_| hull.to_string());
+            let message = match flag {
+                "banned" => format!("{} is banned, but is in fleet", name),
+                "no_xup" => format!("{} joined fleet in a {} without x-ing up", name, hull_name),
+                _ => format!("{} is in fleet in an unapproved {}", name, hull_name),
+            };
+            notification::send(&mut tx, boss_id, Kind::FleetFlag, &message).await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn reassign_members(
+        &self,
+        fleet_id: i64,
+        boss_id: i64,
+        members: &[esi::fleet_members::ESIFleetMember],
     ) -> Result<(), Madness> {
         #[derive(Debug, Serialize)]
-        struct WaitlistUpdate {
-            waitlist_id: i64,
+        struct MoveRequest {
+            role: &'static str,
+            wing_id: i64,
+            squad_id: i64,
+        }
+
+        let squads = sqlx::query!(
+            "SELECT category, squad_id FROM fleet_squad WHERE fleet_id=?",
+            fleet_id
+        )
+        .fetch_all(self.get_db())
+        .await?;
+        let category_of_squad: HashMap<i64, &str> = squads
+            .iter()
+            .map(|squad| (squad.squad_id, squad.category.as_str()))
+            .collect();
+
+        let now = chrono::Utc::now().timestamp();
+        let cooldown_from = now - self.config.fleet_updater.reassign_cooldown;
+        let mut moved = 0;
+
+        for member in members {
+            if moved >= self.config.fleet_updater.max_reassign_per_update {
+                break;
+            }
+            if member.character_id == boss_id {
+                continue;
+            }
+
+            // Members outside the waitlist squads (command, alts) are placed by hand
+            let current = match category_of_squad.get(&member.squad_id) {
+                Some(&category) if category != "alt" => category,
+                _ => continue,
+            };
+
+            let last_moved = self
+                .reassigned_at
+                .lock()
+                .unwrap()
+                .get(&member.character_id)
+                .copied();
+            if last_moved.map_or(false, |moved_at| moved_at > cooldown_from) {
+                continue;
+            }
+
+            let category = match self
+                .categorize_member(member.character_id, member.ship_type_id)
+                .await?
+            {
+                Some(category) => category,
+                None => continue,
+            };
+            if category == current {
+                continue;
+            }
+            if !category_of_squad.values().any(|&c| c == category) {
+                continue;
+            }
+            let (wing_id, squad_id) = match fleet_layout::squad_for_category(
+                self.get_db(),
+                &self.esi_client,
+                fleet_id,
+                boss_id,
+                &category,
+                members,
+            )
+            .await
+            {
+                Ok(squad) => squad,
+                Err(e) => {
+                    warn!(
+                        "Could not find a {} squad in fleet {}: {:#?}",
+                        category, fleet_id, e
+                    );
+                    continue;
+                }
+            };
+
+            if let Err(e) = self
+                .esi_client
+                .put(
+                    &format!("/v1/fleets/{}/members/{}/", fleet_id, member.character_id),
+                    &MoveRequest {
+                        role: "squad_member",
+                        wing_id,
+                        squad_id,
+                    },
+                    boss_id,
+                    ESIScope::Fleets_WriteFleet_v1,
+                )
+                .await
+            {
+                warn!(
+                    "Could not move {} to the {} squad of fleet {}: {:#?}",
+                    member.character_id, category, fleet_id, e
+                );
+            }
+
+            // Failed moves count too, so we don't hammer ESI with a move it keeps refusing
+            self.reassigned_at
+                .lock()
+                .unwrap()
+                .insert(member.character_id, now);
+            moved += 1;
+        }
+
+        self.reassigned_at
+            .lock()
+            .unwrap()
+            .retain(|_, &mut moved_at| moved_at > cooldown_from);
+
+        Ok(())
+    }
+
+    async fn categorize_member(
+        &self,
+        character_id: i64,
+        hull: TypeID,
+    ) -> Result<Option<String>, Madness> {
+        // Some rules look at modules (bastion), so prefer the last fit they x'd up in this hull
+        let last_fit = sqlx::query!(
+            "
+                SELECT dna FROM fit_history
+                JOIN fitting ON fitting.id = fit_history.fit_id
+                WHERE character_id=? AND hull=?
+                ORDER BY fit_history.id DESC LIMIT 1
+            ",
+            character_id,
+            hull
+        )
+        .fetch_optional(self.get_db())
+        .await?;
+
+        let fit = match last_fit.and_then(|fit| Fitting::from_dna(&fit.dna).ok()) {
+            Some(fit) => fit,
+            None => Fitting {
+                hull,
+                modules: BTreeMap::new(),
+                cargo: BTreeMap::new(),
+            },
+        };
+
+        Ok(categories::categorize(&fit))
+    }
+
+    async fn find_new_boss(&self, fleet_id: i64, boss_id: i64) -> Result<Option<i64>, Madness> {
+        // Only someone in the fleet can have taken boss
+        let candidates = sqlx::query!(
+            "
+                SELECT DISTINCT fleet_activity.character_id FROM fleet_activity
+                JOIN refresh_token ON refresh_token.character_id=fleet_activity.character_id
+                WHERE fleet_activity.fleet_id=? AND fleet_activity.has_left=0
+                AND fleet_activity.character_id != ?
+            ",
+            fleet_id,
+            boss_id
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        let mut checks = stream::iter(candidates)
+            .map(|candidate| async move {
+                let character_id = candidate.character_id;
+                match esi::character_fleet::get(&self.esi_client, character_id).await {
+                    Ok(info) if info.fleet_id == fleet_id && info.role == "fleet_commander" => {
+                        Some(character_id)
+                    }
+                    Ok(_) => None,
+                    Err(
+                        esi::ESIError::Status(404)
+                        | esi::ESIError::NoToken
+                        | esi::ESIError::MissingScope,
+                    ) => None,
+                    Err(e) => {
+                        warn!(
+                            "Could not check fleet of {} while looking for a new boss: {:#?}",
+                            character_id, e
+                        );
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(BOSS_LOOKUP_CONCURRENCY);
+
+        while let Some(result) = checks.next().await {
+            if result.is_some() {
+                return Ok(result);
+            }
         }
+
+        Ok(None)
+    }
+
+    async fn hand_over_boss(
+        &self,
+        fleet_id: i64,
+        old_boss_id: i64,
+        new_boss_id: i64,
+    ) -> Result<(), Madness> {
+        let names = character::lookup(self.get_db(), &[old_boss_id, new_boss_id]).await?;
+        let name_of = |id: i64| {
+            names
+                .get(&id)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+        let message = format!(
+            "Fleet boss passed from {} to {}, the fleet registration has moved along",
+            name_of(old_boss_id),
+            name_of(new_boss_id)
+        );
+
+        let mut tx = self.get_db().begin().await?;
+        sqlx::query!(
+            "UPDATE fleet SET boss_id=? WHERE id=?",
+            new_boss_id,
+            fleet_id
+        )
+        .execute(&mut tx)
+        .await?;
+        for &account_id in &[old_boss_id, new_boss_id] {
+            notification::send(&mut tx, account_id, Kind::BossHandover, &message).await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn notify_sse(&self, fleet_id: i64, fleet_comp_changed: bool) -> Result<(), Madness> {
         #[derive(Debug, Serialize)]
         struct NewFleetComp {
             fleet_id: i64,
         }
 
         let mut events = Vec::new();
-        for &id in changed_waitlist_ids {
-            events.push(sse::Event::new_json(
-                "waitlist",
-                "waitlist_update",
-                &WaitlistUpdate { waitlist_id: id },
-            ));
-        }
         if fleet_comp_changed {
             events.push(sse::Event::new_json(
                 "fleet_comp",
@@ -283,9 +908,7 @@ impl FleetUpdater {
             ))
         }
 
-        if !events.is_empty() {
-            self.sse_client.submit(events).await?;
-        }
+        outbox::enqueue(self.get_db(), events).await?;
 
         Ok(())
     }
@@ -0,0 +1,108 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures::future::{self, Either};
+use rand::Rng;
+
+lazy_static::lazy_static! {
+    static ref INSTANCE_ID: String = hex::encode(rand::thread_rng().gen::<[u8; 16]>());
+}
+
+// Background jobs must only run on one backend instance at a time. Each job holds a lease in
+// the database that it renews on every run and, through `run`, while a run is in progress; if the
+// holder goes away, the lease expires and the next instance to try takes the job over.
+pub struct Lease {
+    name: &'static str,
+    duration: i64,
+    held: AtomicBool,
+}
+
+impl Lease {
+    pub fn new(name: &'static str, duration: i64) -> Lease {
+        Lease {
+            name,
+            duration,
+            held: AtomicBool::new(false),
+        }
+    }
+
+    // Takes or renews the lease, returning whether this instance holds it
+    pub async fn acquire(&self, db: &crate::DB) -> Result<bool, sqlx::Error> {
+        self.acquire_for(db, self.duration).await
+    }
+
+    // Runs `job` while holding the lease, renewing it every third of the lease duration. If a
+    // renewal fails the job is dropped at its next await point, so it cannot write anything once
+    // another instance may have taken over; open transactions roll back when they are dropped.
+    pub async fn run<F, E>(&self, db: &crate::DB, job: F) -> Result<(), E>
+    where
+        F: Future<Output = Result<(), E>>,
+        E: From<sqlx::Error>,
+    {
+        if !self.acquire(db).await? {
+            return Ok(());
+        }
+
+        let heartbeat = tokio::time::Duration::from_secs((self.duration / 3).max(1) as u64);
+        let mut job = Box::pin(job);
+        loop {
+            let tick = Box::pin(tokio::time::sleep(heartbeat));
+            match future::select(job, tick).await {
+                Either::Left((result, _)) => return result,
+                Either::Right(((), unfinished)) => job = unfinished,
+            }
+
+            if !self.acquire(db).await? {
+                warn!("Lost the {} lease during a run, abandoning it", self.name);
+                return Ok(());
+            }
+        }
+    }
+
+    // Expiry is judged by the database clock, so instances with skewed clocks agree on it
+    pub async fn acquire_for(&self, db: &crate::DB, duration: i64) -> Result<bool, sqlx::Error> {
+        let holder: &str = &INSTANCE_ID;
+
+        sqlx::query!(
+            "
+                INSERT IGNORE INTO job_lease (name, holder, acquired_at, expires_at)
+                VALUES (?, ?, UNIX_TIMESTAMP(), UNIX_TIMESTAMP() + ?)
+            ",
+            self.name,
+            holder,
+            duration
+        )
+        .execute(db)
+        .await?;
+        // acquired_at goes first, as MySQL assigns left to right and it needs the old holder
+        sqlx::query!(
+            "
+                UPDATE job_lease
+                SET acquired_at=IF(holder=?, acquired_at, UNIX_TIMESTAMP()), holder=?,
+                    expires_at=UNIX_TIMESTAMP() + ?
+                WHERE name=? AND (holder=? OR expires_at <= UNIX_TIMESTAMP())
+            ",
+            holder,
+            holder,
+            duration,
+            self.name,
+            holder
+        )
+        .execute(db)
+        .await?;
+
+        let lease = sqlx::query!("SELECT holder FROM job_lease WHERE name=?", self.name)
+            .fetch_one(db)
+            .await?;
+        let held = lease.holder == holder;
+
+        if held != self.held.swap(held, Ordering::Relaxed) {
+            match held {
+                true => info!("Acquired the {} lease as {}", self.name, holder),
+                false => info!("Lost the {} lease to {}", self.name, lease.holder),
+            }
+        }
+
+        Ok(held)
+    }
+}
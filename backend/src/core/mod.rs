@@ -1,7 +1,21 @@
 pub mod affiliation;
+pub mod announcement;
+pub mod audit;
 pub mod auth;
 pub mod ban;
 pub mod esi;
+pub mod fleet_layout;
 pub mod fleet_updater;
+pub mod lease;
+pub mod notification;
+pub mod outbox;
+pub mod public_status;
+pub mod role_history;
+pub mod role_updater;
 pub mod skill_updater;
 pub mod sse;
+pub mod waitlist_control;
+pub mod waitlist_queue;
+pub mod waitlist_scheduler;
+pub mod waitlist_state;
+pub mod webhook;
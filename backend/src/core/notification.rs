@@ -0,0 +1,135 @@
+use serde::Serialize;
+
+use crate::core::{outbox, sse::Event};
+
+const READ_RETENTION: i64 = 30 * 86400;
+const UNREAD_RETENTION: i64 = 90 * 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Invite,
+    Xup,
+    FleetFlag,
+    BossHandover,
+    RoleExpiry,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Preference {
+    pub inbox: bool,
+    pub sse: bool,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [
+        Kind::Invite,
+        Kind::Xup,
+        Kind::FleetFlag,
+        Kind::BossHandover,
+        Kind::RoleExpiry,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Invite => "invite",
+            Kind::Xup => "xup",
+            Kind::FleetFlag => "fleet_flag",
+            Kind::BossHandover => "boss_handover",
+            Kind::RoleExpiry => "role_expiry",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Kind> {
+        Kind::ALL.iter().copied().find(|k| k.as_str() == kind)
+    }
+
+    // Everything is pushed live unless the account turned it off. X-ups are only interesting while
+    // they happen and would fill every boss's inbox, so they are not kept by default.
+    pub fn default_preference(self) -> Preference {
+        Preference {
+            inbox: self != Kind::Xup,
+            sse: true,
+        }
+    }
+}
+
+pub async fn preference<'c, E>(
+    db: E,
+    account_id: i64,
+    kind: Kind,
+) -> Result<Preference, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    let kind_str = kind.as_str();
+    Ok(
+        match sqlx::query!(
+            "SELECT inbox, sse FROM notification_preference WHERE account_id=? AND kind=?",
+            account_id,
+            kind_str
+        )
+        .fetch_optional(db)
+        .await?
+        {
+            Some(pref) => Preference {
+                inbox: pref.inbox > 0,
+                sse: pref.sse > 0,
+            },
+            None => kind.default_preference(),
+        },
+    )
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    message: &'a str,
+}
+
+// Notifies an account the way it asked to be notified: kept in its inbox, pushed to open tabs, or
+// both. Pass the transaction making the change the notification is about.
+pub async fn send(
+    tx: &mut crate::DBTX<'_>,
+    account_id: i64,
+    kind: Kind,
+    message: &str,
+) -> Result<(), sqlx::Error> {
+    let pref = preference(&mut *tx, account_id, kind).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    if pref.inbox {
+        sqlx::query!(
+            "INSERT INTO notification (account_id, kind, message, created_at) VALUES (?, ?, ?, ?)",
+            account_id,
+            kind.as_str(),
+            message,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    if pref.sse {
+        let topic = format!("account;{}", account_id);
+        // Invites wake the pilot up, everything else shows as a message
+        let event = match kind {
+            Kind::Invite => Event::new(&topic, "wakeup", message.to_string()),
+            _ => Event::new_json(&topic, "message", &Message { message }),
+        };
+        outbox::enqueue(&mut *tx, vec![event]).await?;
+    }
+
+    Ok(())
+}
+
+// Drops read notifications after a month and everything after three months
+pub async fn prune(db: &crate::DB, now: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM notification WHERE created_at < ? AND (read_at IS NOT NULL OR created_at < ?)",
+        now - READ_RETENTION,
+        now - UNREAD_RETENTION
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
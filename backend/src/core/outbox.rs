@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashSet};
+use std::{iter, sync::Arc};
+
+use crate::core::{lease::Lease, notification, sse};
+use crate::{config::Config, util::madness::Madness};
+
+const POLL_INTERVAL_MS: u64 = 500;
+const BATCH_SIZE: usize = 200;
+const MAX_ATTEMPTS: i32 = 10;
+const MAX_BACKOFF: i64 = 60;
+// How long a row missing from the middle of the id sequence is waited for. Ids are handed out
+// when the row is inserted, but the row only shows up once its transaction commits.
+const GAP_TIMEOUT: i64 = 10;
+const RETENTION: i64 = 60 * 60;
+const CLEANUP_INTERVAL: i64 = 60;
+
+// Queues events for delivery. Pass the transaction that makes the change the events are about,
+// so they're only sent if it commits.
+pub async fn enqueue<'c, E>(db: E, events: Vec<sse::Event<'_>>) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let placeholders = iter::repeat("(?, ?, ?, ?, ?)")
+        .take(events.len())
+        .collect::<Vec<_>>()
+        .join(",");
+    let query_str = format!(
+        "INSERT INTO event_outbox (topic, event, data, created_at, next_attempt_at) VALUES {}",
+        placeholders
+    );
+    let mut query = sqlx::query(&query_str);
+    for event in events {
+        query = query
+            .bind(event.topic)
+            .bind(event.event)
+            .bind(event.data)
+            .bind(now)
+            .bind(now);
+    }
+    query.execute(db).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct OutboxRecord {
+    id: i64,
+    topic: String,
+    event: String,
+    data: String,
+    attempts: i32,
+    next_attempt_at: i64,
+}
+
+// Delivers queued events. With an external SSE server one instance (holding the lease) submits
+// everything, retrying failures while holding back later events for the same topic. With the
+// embedded server every instance serves its own streams, so each one tails the outbox instead.
+pub struct OutboxDispatcher {
+    sse_client: sse::SSEClient,
+    db: Arc<crate::DB>,
+    embedded: bool,
+    lease: Lease,
+    last_id: Option<i64>,
+    gaps: BTreeMap<i64, i64>,
+    cleaned_at: i64,
+}
+
+impl OutboxDispatcher {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> OutboxDispatcher {
+        OutboxDispatcher {
+            sse_client: sse::SSEClient::new(&config.sse),
+            db,
+            embedded: config.sse.embedded,
+            lease: Lease::new("event_outbox", 30),
+            last_id: None,
+            gaps: BTreeMap::new(),
+            cleaned_at: 0,
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(mut self) {
+        loop {
+            let sleep_time = match self.run_once().await {
+                Ok(()) => POLL_INTERVAL_MS,
+                Err(e) => {
+                    error!("Error in outbox dispatcher: {:#?}", e);
+                    5000
+                }
+            };
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(sleep_time)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&mut self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+
+        if self.embedded {
+            self.tail().await?;
+        }
+
+        let cleanup_due = now - self.cleaned_at >= CLEANUP_INTERVAL;
+        if !self.embedded || cleanup_due {
+            let this = &*self;
+            this.lease
+                .run(this.get_db(), async {
+                    if !this.embedded {
+                        this.deliver(now).await?;
+                    }
+                    if cleanup_due {
+                        this.cleanup(now).await?;
+                    }
+                    Ok::<(), Madness>(())
+                })
+                .await?;
+            if cleanup_due {
+                self.cleaned_at = now;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn tail(&mut self) -> Result<(), Madness> {
+        let last_id = match self.last_id {
+            Some(id) => id,
+            None => {
+                // Start from whatever is there now, older events have been sent already
+                let id = sqlx::query!("SELECT MAX(id) `id?: i64` FROM event_outbox")
+                    .fetch_one(self.get_db())
+                    .await?
+                    .id
+                    .unwrap_or(0);
+                self.last_id = Some(id);
+                id
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        self.gaps.retain(|_, &mut expires_at| expires_at > now);
+
+        let gap_placeholders: String = iter::repeat(" OR id=?").take(self.gaps.len()).collect();
+        let query_str = format!(
+            "SELECT id, topic, event, data, attempts, next_attempt_at FROM event_outbox WHERE id > ?{} ORDER BY id LIMIT {}",
+            gap_placeholders, BATCH_SIZE
+        );
+        let mut query = sqlx::query_as::<_, OutboxRecord>(&query_str).bind(last_id);
+        for id in self.gaps.keys() {
+            query = query.bind(id);
+        }
+        let records = query.fetch_all(self.get_db()).await?;
+
+        let mut expected = last_id + 1;
+        for record in &records {
+            if !self.gaps.contains_key(&record.id) {
+                for missing in expected..record.id {
+                    self.gaps.insert(missing, now + GAP_TIMEOUT);
+                }
+                expected = record.id + 1;
+                self.last_id = Some(record.id);
+            }
+            self.gaps.remove(&record.id);
+        }
+
+        self.sse_client
+            .submit(
+                records
+                    .iter()
+                    .map(|record| {
+                        sse::Event::new(&record.topic, &record.event, record.data.clone())
+                    })
+                    .collect(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn deliver(&self, now: i64) -> Result<(), Madness> {
+        let records = sqlx::query_as!(
+            OutboxRecord,
+            "
+                SELECT id, topic, event, data, attempts, next_attempt_at FROM event_outbox
+                WHERE delivered_at IS NULL ORDER BY id LIMIT ?
+            ",
+            BATCH_SIZE as i64
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        // Once an event for a topic can't go out, later ones for that topic have to wait for it
+        let mut blocked = HashSet::new();
+        for record in records {
+            if blocked.contains(&record.topic) {
+                continue;
+            }
+            if record.next_attempt_at > now {
+                blocked.insert(record.topic);
+                continue;
+            }
+
+            let event = sse::Event::new(&record.topic, &record.event, record.data.clone());
+            match self.sse_client.submit(vec![event]).await {
+                Ok(()) => {
+                    sqlx::query!(
+                        "UPDATE event_outbox SET delivered_at=?, attempts=attempts+1 WHERE id=?",
+                        now,
+                        record.id
+                    )
+                    .execute(self.get_db())
+                    .await?;
+                }
+                Err(e) if record.attempts + 1 >= MAX_ATTEMPTS => {
+                    error!(
+                        "Dropping {} event {} for {} after {} attempts: {}",
+                        record.event,
+                        record.id,
+                        record.topic,
+                        record.attempts + 1,
+                        e
+                    );
+                    sqlx::query!("DELETE FROM event_outbox WHERE id=?", record.id)
+                        .execute(self.get_db())
+                        .await?;
+                }
+                Err(e) => {
+                    warn!("Could not deliver event {}, will retry: {}", record.id, e);
+                    let backoff = (1i64 << record.attempts.min(6)).min(MAX_BACKOFF);
+                    sqlx::query!(
+                        "UPDATE event_outbox SET attempts=attempts+1, next_attempt_at=? WHERE id=?",
+                        now + backoff,
+                        record.id
+                    )
+                    .execute(self.get_db())
+                    .await?;
+                    blocked.insert(record.topic);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn cleanup(&self, now: i64) -> Result<(), Madness> {
+        // Undelivered events are dropped (or delivered) long before this, unless nothing was
+        // running to deliver them, in which case they're stale anyway
+        sqlx::query!(
+            "DELETE FROM event_outbox WHERE created_at < ?",
+            now - RETENTION
+        )
+        .execute(self.get_db())
+        .await?;
+
+        notification::prune(self.get_db(), now).await?;
+
+        Ok(())
+    }
+}
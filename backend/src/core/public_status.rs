@@ -0,0 +1,211 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::{config::PublicStatusConfig, data, util::madness::Madness};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitlistStatus {
+    id: i64,
+    name: String,
+    // Fits waiting per category name, so a bot doesn't need to know the category ids
+    waiting: BTreeMap<String, i64>,
+    total: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetStatus {
+    size: i64,
+    boss: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    waitlists: Vec<WaitlistStatus>,
+    fleets: Vec<FleetStatus>,
+    updated_at: i64,
+}
+
+// Counts requests per address in fixed windows
+struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    hits: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    fn new(limit: u32, window: Duration) -> RateLimiter {
+        RateLimiter {
+            limit,
+            window,
+            hits: HashMap::new(),
+        }
+    }
+
+    fn allow(&mut self, ip: IpAddr, now: Instant) -> bool {
+        // Forget addresses whose window is over, so the map can't grow without bound
+        if self.hits.len() > 10000 {
+            let window = self.window;
+            self.hits
+                .retain(|_, (started, _)| now.duration_since(*started) < window);
+        }
+
+        let entry = self.hits.entry(ip).or_insert((now, 0));
+        if now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+        }
+        entry.1 += 1;
+        entry.1 <= self.limit
+    }
+}
+
+pub struct StatusService {
+    db: Arc<crate::DB>,
+    config: PublicStatusConfig,
+    cache: Mutex<Option<(Instant, Status)>>,
+    // Held while reloading, so a burst of requests on an expired cache only hits the database once
+    reload: tokio::sync::Mutex<()>,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl StatusService {
+    pub fn new(db: Arc<crate::DB>, config: PublicStatusConfig) -> StatusService {
+        StatusService {
+            db,
+            limiter: Mutex::new(RateLimiter::new(
+                config.requests_per_minute,
+                Duration::from_secs(60),
+            )),
+            cache: Mutex::new(None),
+            reload: tokio::sync::Mutex::new(()),
+            config,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enable
+    }
+
+    pub fn allow(&self, ip: IpAddr) -> bool {
+        self.limiter.lock().unwrap().allow(ip, Instant::now())
+    }
+
+    fn cached(&self) -> Option<Status> {
+        let max_age = Duration::from_secs(self.config.cache_seconds);
+        match &*self.cache.lock().unwrap() {
+            Some((fetched_at, status)) if fetched_at.elapsed() < max_age => Some(status.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn get(&self) -> Result<Status, Madness> {
+        if let Some(status) = self.cached() {
+            return Ok(status);
+        }
+
+        let _reloading = self.reload.lock().await;
+        // Someone else may have reloaded it while we waited
+        if let Some(status) = self.cached() {
+            return Ok(status);
+        }
+
+        let status = self.load().await?;
+        *self.cache.lock().unwrap() = Some((Instant::now(), status.clone()));
+        Ok(status)
+    }
+
+    async fn load(&self) -> Result<Status, Madness> {
+        let category_names: HashMap<&str, &str> = data::categories::categories()
+            .iter()
+            .map(|category| (category.id.as_str(), category.name.as_str()))
+            .collect();
+
+        let mut waitlists: Vec<WaitlistStatus> =
+            sqlx::query!("SELECT id, name FROM waitlist WHERE is_open=1 AND is_archived=0")
+                .fetch_all(self.db.as_ref())
+                .await?
+                .into_iter()
+                .map(|waitlist| WaitlistStatus {
+                    id: waitlist.id,
+                    name: waitlist.name,
+                    waiting: category_names
+                        .values()
+                        .map(|name| (name.to_string(), 0))
+                        .collect(),
+                    total: 0,
+                })
+                .collect();
+
+        // Waiting means the same as for queue positions: approved and not invited yet
+        let counts = sqlx::query!(
+            "
+                SELECT waitlist_id, category, COUNT(*) `count!: i64` FROM waitlist_entry_fit
+                JOIN waitlist_entry ON waitlist_entry.id = entry_id
+                WHERE approved = 1 AND invited_at IS NULL
+                GROUP BY waitlist_id, category
+            "
+        )
+        .fetch_all(self.db.as_ref())
+        .await?;
+        for count in counts {
+            if let Some(waitlist) = waitlists.iter_mut().find(|w| w.id == count.waitlist_id) {
+                let name = category_names
+                    .get(count.category.as_str())
+                    .copied()
+                    .unwrap_or(count.category.as_str());
+                *waitlist.waiting.entry(name.to_string()).or_insert(0) += count.count;
+                waitlist.total += count.count;
+            }
+        }
+
+        let fleets = sqlx::query!(
+            "
+                SELECT
+                    `character`.name AS boss,
+                    (SELECT COUNT(*) FROM fleet_activity WHERE fleet_id=fleet.id AND has_left=0) `size!: i64`
+                FROM fleet
+                JOIN `character` ON `character`.id = boss_id
+            "
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|fleet| FleetStatus {
+            size: fleet.size,
+            boss: fleet.boss,
+        })
+        .collect();
+
+        Ok(Status {
+            waitlists,
+            fleets,
+            updated_at: chrono::Utc::now().timestamp(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let first = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let second = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let start = Instant::now();
+
+        assert!(limiter.allow(first, start));
+        assert!(limiter.allow(first, start + Duration::from_secs(1)));
+        assert!(!limiter.allow(first, start + Duration::from_secs(2)));
+        assert!(limiter.allow(second, start + Duration::from_secs(2)));
+
+        // A new window starts once the old one is over
+        assert!(limiter.allow(first, start + Duration::from_secs(61)));
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+// Append-only record of role grants, changes, revocations and expiries. The `admin` table only
+// holds the current state, so this is the only place that knows who held which role when.
+pub async fn record<'c, E>(
+    db: E,
+    character_id: i64,
+    action: &str,
+    role: &str,
+    previous_role: Option<&str>,
+    expires_at: Option<i64>,
+    changed_by_id: Option<i64>,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    let now = chrono::Utc::now().timestamp();
+
+    sqlx::query!(
+        "
+            INSERT INTO admin_history (character_id, action, role, previous_role, expires_at, changed_by_id, changed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        ",
+        character_id,
+        action,
+        role,
+        previous_role,
+        expires_at,
+        changed_by_id,
+        now
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+struct RoleChange {
+    at: i64,
+    role: Option<String>,
+    expires_at: Option<i64>,
+}
+
+// Replays `admin_history` so statistics can credit activity to the role held at that moment
+pub struct RoleTimeline {
+    changes: HashMap<i64, Vec<RoleChange>>,
+}
+
+impl RoleTimeline {
+    pub async fn load(db: &crate::DB) -> Result<RoleTimeline, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT character_id, action, role, expires_at, changed_at FROM admin_history ORDER BY changed_at, id"
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut timeline = RoleTimeline {
+            changes: HashMap::new(),
+        };
+        for row in rows {
+            timeline.push(
+                row.character_id,
+                &row.action,
+                row.role,
+                row.expires_at,
+                row.changed_at,
+            );
+        }
+        Ok(timeline)
+    }
+
+    // Changes must be pushed in chronological order
+    fn push(
+        &mut self,
+        character_id: i64,
+        action: &str,
+        role: String,
+        expires_at: Option<i64>,
+        at: i64,
+    ) {
+        let role = match action {
+            "revoke" | "expire" => None,
+            _ => Some(role),
+        };
+        self.changes
+            .entry(character_id)
+            .or_insert_with(Vec::new)
+            .push(RoleChange {
+                at,
+                role,
+                expires_at,
+            });
+    }
+
+    pub fn role_at(&self, character_id: i64, at: i64) -> Option<&str> {
+        let changes = self.changes.get(&character_id)?;
+        let idx = changes.partition_point(|change| change.at <= at);
+        let change = &changes[idx.checked_sub(1)?];
+        match change.expires_at {
+            Some(expires_at) if expires_at <= at => None,
+            _ => change.role.as_deref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_at() {
+        let mut timeline = RoleTimeline {
+            changes: HashMap::new(),
+        };
+        timeline.push(1, "grant", "Trainee".to_string(), Some(300), 100);
+        timeline.push(1, "change", "FC".to_string(), None, 200);
+        timeline.push(1, "revoke", "FC".to_string(), None, 400);
+        timeline.push(2, "grant", "Trainee".to_string(), Some(300), 100);
+
+        assert_eq!(timeline.role_at(1, 50), None);
+        assert_eq!(timeline.role_at(1, 100), Some("Trainee"));
+        assert_eq!(timeline.role_at(1, 250), Some("FC"));
+        assert_eq!(timeline.role_at(1, 500), None);
+        // Expired before the cleanup job removed the grant
+        assert_eq!(timeline.role_at(2, 299), Some("Trainee"));
+        assert_eq!(timeline.role_at(2, 300), None);
+        assert_eq!(timeline.role_at(3, 100), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::core::{
+    auth::roles_with_access,
+    lease::Lease,
+    notification::{self, Kind},
+    role_history,
+};
+use crate::{config::Config, util::madness::Madness};
+use std::sync::Arc;
+
+pub struct RoleUpdater {
+    db: Arc<crate::DB>,
+    config: Config,
+    lease: Lease,
+}
+
+impl RoleUpdater {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> RoleUpdater {
+        RoleUpdater {
+            db,
+            config,
+            lease: Lease::new("role_updater", 180),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in role updater: {:#?}", e);
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        self.lease
+            .run(self.get_db(), async {
+                self.remind_expiring().await?;
+                self.remove_expired().await
+            })
+            .await
+    }
+
+    async fn remind_expiring(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let remind_from = now + self.config.role_updater.remind_before;
+
+        let expiring = sqlx::query!(
+            "
+                SELECT character_id, role, expires_at AS `expires_at!`, name FROM admin
+                JOIN `character` ON character_id=`character`.id
+                WHERE expires_at IS NOT NULL AND expires_at > ? AND expires_at <= ? AND reminded_at IS NULL
+            ",
+            now,
+            remind_from
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        if expiring.is_empty() {
+            return Ok(());
+        }
+
+        let active = sqlx::query!(
+            "SELECT character_id, role FROM admin WHERE expires_at IS NULL OR expires_at > ?",
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        for grant in expiring {
+            // Remind everyone who would be able to extend or re-grant the role
+            let trainer_roles = roles_with_access(&format!("commanders-manage:{}", grant.role));
+            let message = format!(
+                "The {} role of {} expires in {} hours",
+                grant.role,
+                grant.name,
+                (grant.expires_at - now) / 3600
+            );
+
+            let mut tx = self.get_db().begin().await?;
+            sqlx::query!(
+                "UPDATE admin SET reminded_at=? WHERE character_id=?",
+                now,
+                grant.character_id
+            )
+            .execute(&mut tx)
+            .await?;
+            for trainer in &active {
+                if trainer_roles.iter().any(|&role| role == trainer.role) {
+                    notification::send(&mut tx, trainer.character_id, Kind::RoleExpiry, &message)
+                        .await?;
+                }
+            }
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_expired(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+
+        let expired = sqlx::query!(
+            "SELECT character_id, role FROM admin WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let mut removed = 0;
+        let mut tx = self.get_db().begin().await?;
+        for grant in &expired {
+            // Re-check the expiry: the grant may have been extended since we selected it
+            let deleted = sqlx::query!(
+                "DELETE FROM admin WHERE character_id=? AND expires_at IS NOT NULL AND expires_at <= ?",
+                grant.character_id,
+                now
+            )
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+            if deleted == 0 {
+                continue;
+            }
+
+            removed += 1;
+            role_history::record(
+                &mut tx,
+                grant.character_id,
+                "expire",
+                &grant.role,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!("Removed {} expired role grants", removed);
+
+        Ok(())
+    }
+}
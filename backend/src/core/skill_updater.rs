@@ -1,4 +1,4 @@
-use crate::core::esi;
+use crate::core::{esi, lease::Lease};
 use crate::data;
 use crate::{config::Config, util::madness::Madness};
 use rand::seq::SliceRandom;
@@ -8,6 +8,7 @@ pub struct SkillUpdater {
     esi_client: esi::ESIClient,
     db: Arc<crate::DB>,
     config: Config,
+    lease: Lease,
 }
 
 impl SkillUpdater {
@@ -20,6 +21,7 @@ impl SkillUpdater {
             ),
             db,
             config,
+            lease: Lease::new("skill_updater", 120),
         }
     }
 
@@ -45,6 +47,10 @@ impl SkillUpdater {
     }
 
     async fn run_once(&self) -> Result<(), Madness> {
+        if !self.lease.acquire(self.get_db()).await? {
+            return Ok(());
+        }
+
         let mut to_update = sqlx::query!("SELECT character_id FROM refresh_token")
             .fetch_all(self.get_db())
             .await?;
@@ -56,7 +62,18 @@ impl SkillUpdater {
         to_update.shuffle(&mut rand::thread_rng());
         let to_update_iter = to_update.into_iter().map(|r| r.character_id);
 
+        // The lease has to outlive the sleep between two characters
+        let lease_duration = (runtime_per_char.ceil() as i64).saturating_add(120);
+
         for character_id in to_update_iter {
+            if !self
+                .lease
+                .acquire_for(self.get_db(), lease_duration)
+                .await?
+            {
+                return Ok(());
+            }
+
             match data::skills::load_skills(&self.esi_client, &self.db, character_id).await {
                 Ok(_) => (),
                 Err(data::skills::SkillsError::ESIError(e)) => {
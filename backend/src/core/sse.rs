@@ -1,10 +1,26 @@
 use branca::Branca;
 use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::config::SSEConfig;
+
+lazy_static::lazy_static! {
+    // Every SSEClient in this process publishes into the same hub, so events from the background
+    // jobs reach the streams served by the routes.
+    static ref EVENT_HUB: broadcast::Sender<HubEvent> = broadcast::channel(1024).0;
+}
 
 pub struct SSEClient {
-    branca: Branca,
-    http: reqwest::Client,
-    url: String,
+    backend: Backend,
+}
+
+enum Backend {
+    External {
+        branca: Branca,
+        http: reqwest::Client,
+        url: String,
+    },
+    Embedded,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -20,9 +36,9 @@ struct SseSubscribe<'a> {
 
 #[derive(Debug, Serialize)]
 pub struct Event<'a> {
-    topic: &'a str,
-    event: &'a str,
-    data: String,
+    pub topic: &'a str,
+    pub event: &'a str,
+    pub data: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,33 +46,67 @@ struct Submission<'a> {
     events: Vec<Event<'a>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct HubEvent {
+    pub topic: String,
+    pub event: String,
+    pub data: String,
+}
+
+pub enum Subscription {
+    Redirect(String),
+    Stream(broadcast::Receiver<HubEvent>),
+}
+
 impl SSEClient {
-    pub fn new(url: String, key: &[u8]) -> SSEClient {
-        SSEClient {
-            url,
-            http: reqwest::Client::new(),
-            branca: Branca::new(key).unwrap(),
-        }
+    pub fn new(config: &SSEConfig) -> SSEClient {
+        let backend = match config.embedded {
+            true => Backend::Embedded,
+            false => Backend::External {
+                url: config.url.clone(),
+                http: reqwest::Client::new(),
+                branca: Branca::new(&hex::decode(&config.secret).unwrap()).unwrap(),
+            },
+        };
+        SSEClient { backend }
     }
 
-    pub fn events_url(&self, topics: &[String]) -> String {
-        let request = SseSubscribe { topics };
-        let payload = rmp_serde::to_vec_named(&request).unwrap();
-        let token = self.branca.clone().encode(&payload).unwrap();
-        format!("{}/events?token={}", self.url, token)
+    pub fn subscribe(&self, topics: &[String]) -> Subscription {
+        match &self.backend {
+            Backend::External { branca, url, .. } => {
+                let request = SseSubscribe { topics };
+                let payload = rmp_serde::to_vec_named(&request).unwrap();
+                let token = branca.clone().encode(&payload).unwrap();
+                Subscription::Redirect(format!("{}/events?token={}", url, token))
+            }
+            Backend::Embedded => Subscription::Stream(EVENT_HUB.subscribe()),
+        }
     }
 
     pub async fn submit(&self, events: Vec<Event<'_>>) -> Result<(), SSEError> {
-        let submission = Submission { events };
-        let payload = rmp_serde::to_vec_named(&submission).unwrap();
-        let encoded = self.branca.clone().encode(&payload).unwrap();
-
-        self.http
-            .post(format!("{}/submit", self.url))
-            .body(encoded)
-            .send()
-            .await?
-            .error_for_status()?;
+        match &self.backend {
+            Backend::External { branca, http, url } => {
+                let submission = Submission { events };
+                let payload = rmp_serde::to_vec_named(&submission).unwrap();
+                let encoded = branca.clone().encode(&payload).unwrap();
+
+                http.post(format!("{}/submit", url))
+                    .body(encoded)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Backend::Embedded => {
+                for event in events {
+                    // Fails only when nobody is listening, which is fine
+                    let _ = EVENT_HUB.send(HubEvent {
+                        topic: event.topic.to_string(),
+                        event: event.event.to_string(),
+                        data: event.data,
+                    });
+                }
+            }
+        }
 
         Ok(())
     }
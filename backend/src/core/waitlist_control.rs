@@ -0,0 +1,89 @@
+use serde_json::json;
+
+use crate::{
+    core::{audit, waitlist_state, webhook},
+    util::madness::Madness,
+};
+
+// Opening, closing and emptying a waitlist, shared by the FC routes and the scheduler. Scheduled
+// actions are attributed to whoever created the schedule, and name the schedule in the audit log.
+
+pub async fn set_open(
+    tx: &mut crate::DBTX<'_>,
+    actor_id: i64,
+    waitlist_id: i64,
+    open: bool,
+    schedule_id: Option<i64>,
+) -> Result<(), Madness> {
+    let name = match sqlx::query!("SELECT name FROM waitlist WHERE id=?", waitlist_id)
+        .fetch_optional(&mut *tx)
+        .await?
+    {
+        Some(waitlist) => waitlist.name,
+        None => return Err(Madness::NotFound("Waitlist not found")),
+    };
+
+    sqlx::query!(
+        "UPDATE waitlist SET is_open=? WHERE id=?",
+        open,
+        waitlist_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let mut payload = json!({ "waitlist_id": waitlist_id, "open": open });
+    if let Some(schedule_id) = schedule_id {
+        payload["schedule_id"] = json!(schedule_id);
+    }
+    audit::log(&mut *tx, actor_id, "waitlist.set_open", None, &payload).await?;
+
+    let (event, message) = match open {
+        true => ("waitlist.open", format!("Waitlist {} is now open", name)),
+        false => ("waitlist.close", format!("Waitlist {} is now closed", name)),
+    };
+    webhook::emit(
+        tx,
+        event,
+        actor_id,
+        message,
+        vec![
+            ("waitlist_id", waitlist_id.to_string()),
+            ("waitlist_name", name),
+        ],
+    )
+    .await?;
+
+    waitlist_state::publish_reload(tx, waitlist_id).await
+}
+
+pub async fn empty(
+    tx: &mut crate::DBTX<'_>,
+    actor_id: i64,
+    waitlist_id: i64,
+    schedule_id: Option<i64>,
+) -> Result<(), Madness> {
+    sqlx::query!(
+        "
+            DELETE FROM waitlist_entry_fit
+            WHERE entry_id IN (SELECT id FROM waitlist_entry WHERE waitlist_id=?)
+        ",
+        waitlist_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM waitlist_entry WHERE waitlist_id=?",
+        waitlist_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let mut payload = json!({ "waitlist_id": waitlist_id });
+    if let Some(schedule_id) = schedule_id {
+        payload["schedule_id"] = json!(schedule_id);
+    }
+    audit::log(&mut *tx, actor_id, "waitlist.empty", None, &payload).await?;
+
+    waitlist_state::publish_reload(tx, waitlist_id).await
+}
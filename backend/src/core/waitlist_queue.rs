@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::core::waitlist_state::FitRecord;
+
+// Estimates only look at the last month of invites, and only once a category has a few of them
+const HISTORY: i64 = 30 * 86400;
+const MIN_SAMPLES: usize = 5;
+// Longer waits are x-ups left sitting while no fleet was running, they don't say anything about pace
+const MAX_WAIT: i64 = 4 * 3600;
+const CACHE_TIME: Duration = Duration::from_secs(60);
+
+// Where one of the viewer's own x-ups stands
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct QueuePlace {
+    pub position: Option<i64>,
+    pub eta: Option<i64>,
+}
+
+impl QueuePlace {
+    pub fn new(position: Option<i64>, wait_per_place: Option<i64>, now: i64) -> QueuePlace {
+        QueuePlace {
+            position,
+            eta: match (position, wait_per_place) {
+                (Some(position), Some(wait)) => Some(eta(position, wait, now)),
+                _ => None,
+            },
+        }
+    }
+}
+
+// One past invite, from `waitlist_invite`
+#[derive(Debug, Clone, Copy)]
+struct Invite {
+    waitlist_id: i64,
+    xup_at: i64,
+    invited_at: i64,
+}
+
+// Numbers the approved x-ups that are still waiting for an invite, per category, in the order
+// they came in. Expects the records in waitlist order, as `load_fits` returns them.
+pub fn positions(records: &[FitRecord]) -> HashMap<i64, i64> {
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    let mut positions = HashMap::new();
+    for record in records {
+        if record.wef_approved == 0 || record.wef_invited_at.is_some() {
+            continue;
+        }
+        let count = counts.entry(record.wef_category.as_str()).or_insert(0);
+        *count += 1;
+        positions.insert(record.wef_id, *count);
+    }
+    positions
+}
+
+fn median(values: &mut [i64]) -> Option<i64> {
+    if values.len() < MIN_SAMPLES {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+// Typical x-up to invite time per place in line, for the invites of one category. An x-up's place
+// is taken to be one more than the number of earlier x-ups on the same waitlist that were invited
+// while it waited.
+fn wait_per_place(invites: &mut [Invite]) -> Option<i64> {
+    // Of two x-ups that came in together, the one invited first was ahead
+    invites.sort_unstable_by_key(|invite| (invite.xup_at, invite.invited_at));
+
+    let mut samples = Vec::new();
+    for (i, invite) in invites.iter().enumerate() {
+        let wait = invite.invited_at - invite.xup_at;
+        if wait < 0 || wait > MAX_WAIT {
+            continue;
+        }
+        let ahead = invites[..i]
+            .iter()
+            .rev()
+            .take_while(|other| invite.xup_at - other.xup_at <= MAX_WAIT)
+            .filter(|other| {
+                other.waitlist_id == invite.waitlist_id
+                    && other.invited_at > invite.xup_at
+                    && other.invited_at <= invite.invited_at
+            })
+            .count() as i64;
+        samples.push(wait / (ahead + 1));
+    }
+    median(&mut samples)
+}
+
+// The pilot is invited once everyone ahead of them is, at the pace past x-ups in the category moved
+pub fn eta(position: i64, wait_per_place: i64, now: i64) -> i64 {
+    now + position * wait_per_place
+}
+
+// Wait per place in line for each category. It changes slowly, so it is kept for a minute rather
+// than recomputed for every waitlist request.
+pub struct QueueEstimates {
+    db: Arc<crate::DB>,
+    cache: Mutex<Option<(Instant, Arc<HashMap<String, i64>>)>>,
+    // Held while reloading, so only one request at a time hits the database
+    reload: tokio::sync::Mutex<()>,
+}
+
+impl QueueEstimates {
+    pub fn new(db: Arc<crate::DB>) -> QueueEstimates {
+        QueueEstimates {
+            db,
+            cache: Mutex::new(None),
+            reload: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn cached(&self) -> Option<Arc<HashMap<String, i64>>> {
+        match &*self.cache.lock().unwrap() {
+            Some((fetched_at, waits)) if fetched_at.elapsed() < CACHE_TIME => Some(waits.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn wait_per_place(&self) -> Result<Arc<HashMap<String, i64>>, sqlx::Error> {
+        if let Some(waits) = self.cached() {
+            return Ok(waits);
+        }
+
+        let _reloading = self.reload.lock().await;
+        if let Some(waits) = self.cached() {
+            return Ok(waits);
+        }
+
+        let waits = Arc::new(self.load().await?);
+        *self.cache.lock().unwrap() = Some((Instant::now(), waits.clone()));
+        Ok(waits)
+    }
+
+    async fn load(&self) -> Result<HashMap<String, i64>, sqlx::Error> {
+        let since = chrono::Utc::now().timestamp() - HISTORY;
+        let mut invites: HashMap<String, Vec<Invite>> = HashMap::new();
+        for invite in sqlx::query!(
+            "SELECT waitlist_id, category, xup_at, invited_at FROM waitlist_invite WHERE invited_at > ?",
+            since
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        {
+            invites.entry(invite.category).or_default().push(Invite {
+                waitlist_id: invite.waitlist_id,
+                xup_at: invite.xup_at,
+                invited_at: invite.invited_at,
+            });
+        }
+
+        Ok(invites
+            .into_iter()
+            .filter_map(|(category, mut invites)| {
+                wait_per_place(&mut invites).map(|wait| (category, wait))
+            })
+            .collect())
+    }
+}
+
+// Only the first invite of an x-up counts, re-invites would skew the waits
+pub async fn record_invite(
+    tx: &mut crate::DBTX<'_>,
+    waitlist_id: i64,
+    character_id: i64,
+    fleet_id: i64,
+    category: &str,
+    xup_at: i64,
+    invited_at: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO waitlist_invite (waitlist_id, character_id, fleet_id, category, xup_at, invited_at) VALUES (?, ?, ?, ?, ?, ?)",
+        waitlist_id,
+        character_id,
+        fleet_id,
+        category,
+        xup_at,
+        invited_at
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(entry_id: i64, fit_id: i64, category: &str, approved: bool) -> FitRecord {
+        FitRecord {
+            we_id: entry_id,
+            we_joined_at: 0,
+            we_account_id: entry_id,
+            wef_id: fit_id,
+            wef_approved: approved as i8,
+            wef_category: category.to_string(),
+            wef_cached_time_in_fleet: 0,
+            wef_review_comment: None,
+            wef_tags: String::new(),
+            wef_fit_analysis: None,
+            wef_is_alt: 0,
+            wef_invited_at: None,
+            char_wef_id: entry_id,
+            char_wef_name: String::new(),
+            char_we_id: entry_id,
+            char_we_name: String::new(),
+            fitting_dna: String::new(),
+            fitting_hull: 0,
+            implant_set_implants: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut invited = record(1, 2, "dps", true);
+        invited.wef_invited_at = Some(100);
+        let records = vec![
+            record(1, 1, "logi", true),
+            invited,
+            record(2, 3, "dps", false),
+            record(2, 4, "dps", true),
+            record(3, 5, "logi", true),
+            record(3, 6, "dps", true),
+        ];
+
+        let positions = positions(&records);
+        assert_eq!(positions.get(&1), Some(&1));
+        assert_eq!(positions.get(&2), None);
+        assert_eq!(positions.get(&3), None);
+        assert_eq!(positions.get(&4), Some(&1));
+        assert_eq!(positions.get(&5), Some(&2));
+        assert_eq!(positions.get(&6), Some(&2));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut vec![300, 100, 200, 500]), None);
+        assert_eq!(median(&mut vec![300, 100, 200, 500, 400]), Some(300));
+    }
+
+    fn invite(waitlist_id: i64, xup_at: i64, invited_at: i64) -> Invite {
+        Invite {
+            waitlist_id,
+            xup_at,
+            invited_at,
+        }
+    }
+
+    #[test]
+    fn test_wait_per_place() {
+        assert_eq!(wait_per_place(&mut [invite(1, 0, 60)]), None);
+
+        // Five pilots x-up together and are invited a minute apart, so each place takes a minute.
+        // The invite on the other waitlist doesn't hold anyone up, and the x-up left overnight
+        // is ignored.
+        let mut invites = vec![
+            invite(1, 0, 60),
+            invite(1, 0, 120),
+            invite(1, 0, 180),
+            invite(1, 0, 240),
+            invite(1, 0, 300),
+            invite(2, 100, 150),
+            invite(1, 300, 300 + 86400),
+        ];
+        assert_eq!(wait_per_place(&mut invites), Some(60));
+    }
+
+    #[test]
+    fn test_queue_place() {
+        let first = QueuePlace::new(Some(1), Some(120), 1000);
+        let twentieth = QueuePlace::new(Some(20), Some(120), 1000);
+        assert_eq!(first.eta, Some(1120));
+        assert_eq!(twentieth.eta, Some(3400));
+        assert_eq!(QueuePlace::new(Some(1), None, 1000).eta, None);
+        assert_eq!(QueuePlace::new(None, Some(120), 1000).eta, None);
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use crate::core::{lease::Lease, waitlist_control};
+use crate::util::madness::Madness;
+
+pub struct WaitlistScheduler {
+    db: Arc<crate::DB>,
+    lease: Lease,
+}
+
+// Moves a recurring window forward by whole periods until it closes in the future
+fn next_window(opens_at: i64, closes_at: i64, every: i64, now: i64) -> (i64, i64) {
+    let periods = (now - closes_at) / every + 1;
+    (opens_at + periods * every, closes_at + periods * every)
+}
+
+impl WaitlistScheduler {
+    pub fn new(db: Arc<crate::DB>) -> WaitlistScheduler {
+        WaitlistScheduler {
+            db,
+            lease: Lease::new("waitlist_scheduler", 60),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in waitlist scheduler: {:#?}", e);
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        self.lease
+            .run(self.get_db(), async {
+                self.open_due().await?;
+                self.close_due().await
+            })
+            .await
+    }
+
+    async fn open_due(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let due = sqlx::query!(
+            "
+                SELECT waitlist_schedule.id, waitlist_id, created_by_id, is_open FROM waitlist_schedule
+                JOIN waitlist ON waitlist.id = waitlist_id
+                WHERE is_opened=0 AND opens_at <= ? AND closes_at > ?
+            ",
+            now,
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        for schedule in due {
+            let mut tx = self.get_db().begin().await?;
+            // A list that an FC already opened still gets closed when the window ends
+            if schedule.is_open == 0 {
+                waitlist_control::set_open(
+                    &mut tx,
+                    schedule.created_by_id,
+                    schedule.waitlist_id,
+                    true,
+                    Some(schedule.id),
+                )
+                .await?;
+            }
+            sqlx::query!(
+                "UPDATE waitlist_schedule SET is_opened=1 WHERE id=?",
+                schedule.id
+            )
+            .execute(&mut tx)
+            .await?;
+            tx.commit().await?;
+
+            info!(
+                "Opened waitlist {} for schedule {}",
+                schedule.waitlist_id, schedule.id
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn close_due(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let due = sqlx::query!(
+            "
+                SELECT
+                    waitlist_schedule.id, waitlist_id, opens_at, closes_at, repeat_every,
+                    empty_on_close, is_opened, created_by_id, is_open
+                FROM waitlist_schedule
+                JOIN waitlist ON waitlist.id = waitlist_id
+                WHERE closes_at <= ?
+            ",
+            now
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        for schedule in due {
+            let mut tx = self.get_db().begin().await?;
+
+            // Windows that were missed entirely (e.g. during downtime) are skipped, not replayed
+            if schedule.is_opened > 0 {
+                if schedule.is_open > 0 {
+                    waitlist_control::set_open(
+                        &mut tx,
+                        schedule.created_by_id,
+                        schedule.waitlist_id,
+                        false,
+                        Some(schedule.id),
+                    )
+                    .await?;
+                }
+                if schedule.empty_on_close > 0 {
+                    waitlist_control::empty(
+                        &mut tx,
+                        schedule.created_by_id,
+                        schedule.waitlist_id,
+                        Some(schedule.id),
+                    )
+                    .await?;
+                }
+            }
+
+            match schedule.repeat_every {
+                Some(every) => {
+                    let (opens_at, closes_at) =
+                        next_window(schedule.opens_at, schedule.closes_at, every, now);
+                    sqlx::query!(
+                        "UPDATE waitlist_schedule SET opens_at=?, closes_at=?, is_opened=0 WHERE id=?",
+                        opens_at,
+                        closes_at,
+                        schedule.id
+                    )
+                    .execute(&mut tx)
+                    .await?;
+                }
+                None => {
+                    sqlx::query!("DELETE FROM waitlist_schedule WHERE id=?", schedule.id)
+                        .execute(&mut tx)
+                        .await?;
+                }
+            }
+            tx.commit().await?;
+
+            if schedule.is_opened > 0 {
+                info!(
+                    "Closed waitlist {} for schedule {}",
+                    schedule.waitlist_id, schedule.id
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_window;
+
+    #[test]
+    fn test_next_window() {
+        let week = 7 * 86400;
+        // Just closed: the next window is a week later
+        assert_eq!(
+            next_window(1000, 5000, week, 5000),
+            (1000 + week, 5000 + week)
+        );
+        // Missed three windows: skip straight to the upcoming one
+        assert_eq!(
+            next_window(1000, 5000, week, 5000 + 3 * week - 1),
+            (1000 + 3 * week, 5000 + 3 * week)
+        );
+        assert_eq!(
+            next_window(1000, 5000, week, 5000 + 3 * week),
+            (1000 + 4 * week, 5000 + 4 * week)
+        );
+    }
+}
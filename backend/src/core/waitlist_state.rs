@@ -0,0 +1,445 @@
+use std::collections::{BTreeSet, HashMap};
+
+use rocket::serde::json::Value;
+use serde::Serialize;
+
+use crate::{
+    core::{auth::AuthenticatedAccount, outbox, sse::Event, waitlist_queue::QueuePlace},
+    data,
+    util::{
+        madness::Madness,
+        types::{Character, Hull},
+    },
+};
+use eve_data_core::{TypeDB, TypeID};
+
+// How much of the waitlist a viewer gets to see. Everyone sees their own x-ups in full.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Visibility {
+    Public,
+    Names,
+    Full,
+}
+
+impl Visibility {
+    pub fn of(account: &AuthenticatedAccount) -> Visibility {
+        if !account.access.contains("waitlist-view") {
+            Visibility::Public
+        } else if account.access.contains("fit-view") {
+            Visibility::Full
+        } else {
+            Visibility::Names
+        }
+    }
+
+    // Deltas are rendered once per visibility, each viewer subscribes to the one they may see
+    pub fn topic(self) -> &'static str {
+        match self {
+            Visibility::Public => "waitlist;public",
+            Visibility::Names => "waitlist;names",
+            Visibility::Full => "waitlist;full",
+        }
+    }
+}
+
+pub struct FitRecord {
+    pub we_id: i64,
+    pub we_joined_at: i64,
+    pub we_account_id: i64,
+    pub wef_id: i64,
+    pub wef_approved: i8,
+    pub wef_category: String,
+    pub wef_cached_time_in_fleet: i64,
+    pub wef_review_comment: Option<String>,
+    pub wef_tags: String,
+    pub wef_fit_analysis: Option<String>,
+    pub wef_is_alt: i8,
+    pub wef_invited_at: Option<i64>,
+    pub char_wef_id: i64,
+    pub char_wef_name: String,
+    pub char_we_id: i64,
+    pub char_we_name: String,
+    pub fitting_dna: String,
+    pub fitting_hull: i32,
+    pub implant_set_implants: String,
+}
+
+// Loads the x-ups of a waitlist, or a single one of them
+pub async fn load_fits<'c, E>(
+    db: E,
+    waitlist_id: i64,
+    fit_id: Option<i64>,
+) -> Result<Vec<FitRecord>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    sqlx::query_as!(
+        FitRecord,
+        "
+            SELECT
+                we.id we_id,
+                we.joined_at we_joined_at,
+                we.account_id we_account_id,
+                wef.id wef_id,
+                wef.approved wef_approved,
+                wef.category wef_category,
+                wef.cached_time_in_fleet wef_cached_time_in_fleet,
+                wef.review_comment wef_review_comment,
+                wef.tags wef_tags,
+                wef.fit_analysis wef_fit_analysis,
+                wef.is_alt wef_is_alt,
+                wef.invited_at wef_invited_at,
+                char_wef.id char_wef_id,
+                char_wef.name char_wef_name,
+                char_we.id char_we_id,
+                char_we.name char_we_name,
+                fitting.dna fitting_dna,
+                fitting.hull fitting_hull,
+                implant_set.implants implant_set_implants
+                FROM waitlist_entry_fit wef
+            JOIN waitlist_entry we ON wef.entry_id = we.id
+            JOIN `character` char_wef ON wef.character_id = char_wef.id
+            JOIN `character` char_we ON we.account_id = char_we.id
+            JOIN fitting ON wef.fit_id = fitting.id
+            JOIN implant_set ON wef.implant_set_id = implant_set.id
+            WHERE we.waitlist_id = ? AND (? IS NULL OR wef.id = ?)
+            ORDER BY we.id ASC, wef.id ASC
+        ",
+        waitlist_id,
+        fit_id,
+        fit_id
+    )
+    .fetch_all(db)
+    .await
+}
+
+#[derive(Debug, Serialize)]
+pub struct WaitlistEntryFit {
+    id: i64,
+    approved: bool,
+    category: String,
+    hull: Hull,
+    character: Option<Character>,
+    tags: Vec<String>,
+    hours_in_fleet: Option<i64>,
+    review_comment: Option<String>,
+    dna: Option<String>,
+    implants: Option<Vec<TypeID>>,
+    fit_analysis: Option<Value>,
+    is_alt: bool,
+    invited_at: Option<i64>,
+    // Only set on the viewer's own x-ups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue: Option<QueuePlace>,
+}
+
+impl WaitlistEntryFit {
+    pub fn set_queue(&mut self, queue: QueuePlace) {
+        self.queue = Some(queue);
+    }
+}
+
+pub fn entry_character(record: &FitRecord, visibility: Visibility) -> Option<Character> {
+    match visibility >= Visibility::Names {
+        true => Some(Character {
+            id: record.char_we_id,
+            name: record.char_we_name.clone(),
+            corporation_id: None,
+        }),
+        false => None,
+    }
+}
+
+pub fn fit_view(record: &FitRecord, visibility: Visibility, hull: Hull) -> WaitlistEntryFit {
+    let category = data::categories::categories()
+        .iter()
+        .find(|cat| cat.id == record.wef_category)
+        .map(|cat| cat.name.clone())
+        .unwrap_or_else(|| record.wef_category.clone());
+    let tags = record
+        .wef_tags
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+
+    let mut fit = WaitlistEntryFit {
+        id: record.wef_id,
+        approved: record.wef_approved > 0,
+        category,
+        hull,
+        character: None,
+        tags: Vec::new(),
+        hours_in_fleet: None,
+        review_comment: None,
+        dna: None,
+        implants: None,
+        fit_analysis: None,
+        is_alt: record.wef_is_alt > 0,
+        invited_at: record.wef_invited_at,
+        queue: None,
+    };
+
+    if visibility >= Visibility::Names {
+        fit.character = Some(Character {
+            id: record.char_wef_id,
+            name: record.char_wef_name.clone(),
+            corporation_id: None,
+        });
+        fit.hours_in_fleet = Some(record.wef_cached_time_in_fleet / 3600);
+        fit.review_comment = record.wef_review_comment.clone();
+        fit.tags = tags.collect();
+    } else {
+        fit.tags = tags
+            .filter(|t| data::tags::public_tags().contains(t))
+            .collect();
+    }
+
+    if visibility == Visibility::Full {
+        fit.dna = Some(record.fitting_dna.clone());
+        fit.implants = Some(
+            record
+                .implant_set_implants
+                .split(':')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<TypeID>().unwrap())
+                .collect(),
+        );
+        if let Some(fit_analysis) = &record.wef_fit_analysis {
+            fit.fit_analysis = rocket::serde::json::from_str(fit_analysis).unwrap();
+        }
+    }
+
+    fit
+}
+
+// A change to a waitlist, as recorded by whoever made it
+#[derive(Debug, Clone, Copy)]
+pub enum Change {
+    FitAdded { fit_id: i64 },
+    FitRemoved { entry_id: i64, fit_id: i64 },
+    EntryRemoved { entry_id: i64 },
+    FitApproved { fit_id: i64 },
+    FitRejected { fit_id: i64 },
+    FitInvited { fit_id: i64 },
+}
+
+#[derive(Debug, Serialize)]
+struct DeltaEntry {
+    id: i64,
+    character: Option<Character>,
+    joined_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Delta {
+    FitAdded {
+        entry: DeltaEntry,
+        fit: WaitlistEntryFit,
+    },
+    FitRemoved {
+        entry_id: i64,
+        fit_id: i64,
+    },
+    EntryRemoved {
+        entry_id: i64,
+    },
+    FitApproved {
+        fit_id: i64,
+    },
+    FitRejected {
+        fit_id: i64,
+        review_comment: Option<String>,
+    },
+    FitInvited {
+        fit_id: i64,
+        invited_at: Option<i64>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct WaitlistDelta {
+    waitlist_id: i64,
+    version: i64,
+    deltas: Vec<Delta>,
+}
+
+#[derive(Debug, Serialize)]
+struct OwnFit {
+    waitlist_id: i64,
+    version: i64,
+    entry: DeltaEntry,
+    fit: WaitlistEntryFit,
+}
+
+async fn bump_version(tx: &mut crate::DBTX<'_>, waitlist_id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query!(
+        "UPDATE waitlist SET version=version+1 WHERE id=?",
+        waitlist_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(
+        sqlx::query!("SELECT version FROM waitlist WHERE id=?", waitlist_id)
+            .fetch_one(&mut *tx)
+            .await?
+            .version,
+    )
+}
+
+fn hull_of(record: &FitRecord) -> Result<Hull, Madness> {
+    Ok(Hull {
+        id: record.fitting_hull as TypeID,
+        name: TypeDB::name_of(record.fitting_hull as TypeID)?,
+    })
+}
+
+fn render(
+    changes: &[Change],
+    records: &HashMap<i64, FitRecord>,
+    visibility: Visibility,
+) -> Result<Vec<Delta>, Madness> {
+    let mut deltas = Vec::new();
+    for change in changes {
+        deltas.push(match *change {
+            Change::FitAdded { fit_id } => {
+                let record = match records.get(&fit_id) {
+                    Some(record) => record,
+                    None => continue,
+                };
+                Delta::FitAdded {
+                    entry: DeltaEntry {
+                        id: record.we_id,
+                        character: entry_character(record, visibility),
+                        joined_at: record.we_joined_at,
+                    },
+                    fit: fit_view(record, visibility, hull_of(record)?),
+                }
+            }
+            Change::FitRemoved { entry_id, fit_id } => Delta::FitRemoved { entry_id, fit_id },
+            Change::EntryRemoved { entry_id } => Delta::EntryRemoved { entry_id },
+            Change::FitApproved { fit_id } => Delta::FitApproved { fit_id },
+            Change::FitRejected { fit_id } => Delta::FitRejected {
+                fit_id,
+                review_comment: match visibility >= Visibility::Names {
+                    true => records
+                        .get(&fit_id)
+                        .and_then(|record| record.wef_review_comment.clone()),
+                    false => None,
+                },
+            },
+            Change::FitInvited { fit_id } => Delta::FitInvited {
+                fit_id,
+                invited_at: records
+                    .get(&fit_id)
+                    .and_then(|record| record.wef_invited_at),
+            },
+        });
+    }
+    Ok(deltas)
+}
+
+// Bumps the waitlist version and queues the changes for every kind of viewer, plus the full
+// details of added or rejected fits for their owners. Call this from the transaction making the
+// changes; clients that see a version they didn't expect refetch the waitlist.
+pub async fn publish(
+    tx: &mut crate::DBTX<'_>,
+    waitlist_id: i64,
+    changes: &[Change],
+) -> Result<(), Madness> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let version = bump_version(tx, waitlist_id).await?;
+
+    let mut records = HashMap::new();
+    for change in changes {
+        if let Change::FitAdded { fit_id }
+        | Change::FitRejected { fit_id }
+        | Change::FitInvited { fit_id } = *change
+        {
+            for record in load_fits(&mut *tx, waitlist_id, Some(fit_id)).await? {
+                records.insert(record.wef_id, record);
+            }
+        }
+    }
+
+    let mut payloads = Vec::new();
+    for &visibility in &[Visibility::Public, Visibility::Names, Visibility::Full] {
+        payloads.push((
+            visibility.topic().to_string(),
+            "waitlist_delta",
+            serde_json::to_string(&WaitlistDelta {
+                waitlist_id,
+                version,
+                deltas: render(changes, &records, visibility)?,
+            })
+            .unwrap(),
+        ));
+    }
+
+    let owned: BTreeSet<i64> = changes
+        .iter()
+        .filter_map(|change| match *change {
+            Change::FitAdded { fit_id } | Change::FitRejected { fit_id } => Some(fit_id),
+            _ => None,
+        })
+        .collect();
+    for fit_id in owned {
+        let record = match records.get(&fit_id) {
+            Some(record) => record,
+            None => continue,
+        };
+        payloads.push((
+            format!("account;{}", record.we_account_id),
+            "waitlist_own_fit",
+            serde_json::to_string(&OwnFit {
+                waitlist_id,
+                version,
+                entry: DeltaEntry {
+                    id: record.we_id,
+                    character: entry_character(record, Visibility::Full),
+                    joined_at: record.we_joined_at,
+                },
+                fit: fit_view(record, Visibility::Full, hull_of(record)?),
+            })
+            .unwrap(),
+        ));
+    }
+
+    outbox::enqueue(
+        &mut *tx,
+        payloads
+            .iter()
+            .map(|(topic, event, data)| Event::new(topic, event, data.clone()))
+            .collect(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct WaitlistUpdate {
+    waitlist_id: i64,
+    version: i64,
+}
+
+// For changes that don't fit in a delta (opening, closing, emptying): clients refetch
+pub async fn publish_reload(tx: &mut crate::DBTX<'_>, waitlist_id: i64) -> Result<(), Madness> {
+    let version = bump_version(tx, waitlist_id).await?;
+    outbox::enqueue(
+        &mut *tx,
+        vec![Event::new_json(
+            "waitlist",
+            "waitlist_update",
+            &WaitlistUpdate {
+                waitlist_id,
+                version,
+            },
+        )],
+    )
+    .await?;
+    Ok(())
+}
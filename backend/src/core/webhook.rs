@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::core::lease::Lease;
+use crate::util::{madness::Madness, template};
+
+// Events a webhook can subscribe to
+pub const EVENTS: &[&str] = &[
+    "waitlist.open",
+    "waitlist.close",
+    "fleet.register",
+    "fleet.close",
+    "announcement.create",
+    "ban.issue",
+];
+
+// Small enough that a batch of timed out requests still finishes well within the lease
+const BATCH_SIZE: i64 = 5;
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_BACKOFF: i64 = 30;
+const MAX_BACKOFF: i64 = 60 * 60;
+const REQUEST_TIMEOUT: u64 = 10;
+// A claimed delivery is not picked up again until this has passed, even if we die while posting
+const CLAIM_TIMEOUT: i64 = 5 * 60;
+
+// HMAC-SHA256 over "<timestamp>.<body>", so a receiver can reject replayed requests
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Templates are JSON documents with placeholders inside strings, so the values are escaped for
+// use in a JSON string. Without a template, the message goes out the way Discord expects it.
+pub fn render_payload(template: Option<&str>, vars: &HashMap<&str, String>) -> String {
+    match template {
+        Some(template) => {
+            let escaped = vars
+                .iter()
+                .map(|(&key, value)| {
+                    let quoted = serde_json::to_string(value).unwrap();
+                    (key, quoted[1..quoted.len() - 1].to_string())
+                })
+                .collect();
+            template::render(template, &escaped)
+        }
+        None => serde_json::json!({
+            "content": vars.get("message").cloned().unwrap_or_default()
+        })
+        .to_string(),
+    }
+}
+
+// Checks that a template renders to valid JSON whatever ends up in the placeholders
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let vars = ["event", "message", "actor", "timestamp"]
+        .iter()
+        .map(|&key| (key, "\"sample\"\n".to_string()))
+        .collect();
+    match serde_json::from_str::<serde_json::Value>(&render_payload(Some(template), &vars)) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Template is not valid JSON: {}", e)),
+    }
+}
+
+// Queues the event for every enabled webhook subscribed to it. The message is the default text,
+// templates can also use the event-specific values in `vars`.
+pub async fn emit(
+    tx: &mut crate::DBTX<'_>,
+    event: &str,
+    actor_id: i64,
+    message: String,
+    vars: Vec<(&str, String)>,
+) -> Result<(), sqlx::Error> {
+    let webhooks = sqlx::query!("SELECT id, events, template FROM webhook WHERE enabled=1")
+        .fetch_all(&mut *tx)
+        .await?;
+    let webhooks: Vec<_> = webhooks
+        .into_iter()
+        .filter(|webhook| webhook.events.split(',').any(|e| e == event))
+        .collect();
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let actor = sqlx::query!("SELECT name FROM `character` WHERE id=?", actor_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|character| character.name)
+        .unwrap_or_else(|| actor_id.to_string());
+
+    let mut all_vars: HashMap<&str, String> = vars.into_iter().collect();
+    all_vars.insert("event", event.to_string());
+    all_vars.insert("message", message);
+    all_vars.insert("actor", actor);
+    all_vars.insert("timestamp", now.to_string());
+
+    for webhook in webhooks {
+        let payload = render_payload(webhook.template.as_deref(), &all_vars);
+        queue(&mut *tx, webhook.id, event, &payload).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn queue<'c, E>(
+    db: E,
+    webhook_id: i64,
+    event: &str,
+    payload: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = crate::DBEngine>,
+{
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query!(
+        "
+            INSERT INTO webhook_delivery (webhook_id, event, payload, status, next_attempt_at, created_at)
+            VALUES (?, ?, ?, 'pending', ?, ?)
+        ",
+        webhook_id,
+        event,
+        payload,
+        now,
+        now
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct DeliveryError {
+    pub message: String,
+    pub retry_after: Option<i64>,
+}
+
+pub async fn post(
+    http: &reqwest::Client,
+    url: &str,
+    secret: Option<&str>,
+    body: &str,
+) -> Result<(), DeliveryError> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let mut request = http
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Waitlist-Timestamp", timestamp.to_string())
+        .body(body.to_string());
+    if let Some(secret) = secret {
+        request = request.header(
+            "X-Waitlist-Signature",
+            format!("sha256={}", sign(secret, timestamp, body)),
+        );
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return Err(DeliveryError {
+                message: e.to_string(),
+                retry_after: None,
+            })
+        }
+    };
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    // Discord asks rate limited clients to come back later
+    let retry_after = response
+        .headers()
+        .get("Retry-After")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok())
+        .map(|seconds| seconds.ceil() as i64);
+    Err(DeliveryError {
+        message: format!("HTTP {}", response.status()),
+        retry_after,
+    })
+}
+
+pub struct WebhookDispatcher {
+    http: reqwest::Client,
+    db: Arc<crate::DB>,
+    lease: Lease,
+}
+
+impl WebhookDispatcher {
+    pub fn new(db: Arc<crate::DB>) -> WebhookDispatcher {
+        WebhookDispatcher {
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT))
+                .build()
+                .unwrap(),
+            db,
+            lease: Lease::new("webhook_dispatcher", 60),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in webhook dispatcher: {:#?}", e);
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        self.lease.run(self.get_db(), self.dispatch()).await
+    }
+
+    async fn dispatch(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let deliveries = sqlx::query!(
+            "
+                SELECT webhook_delivery.id, payload, attempts, url, secret FROM webhook_delivery
+                JOIN webhook ON webhook.id = webhook_id
+                WHERE status='pending' AND next_attempt_at <= ?
+                ORDER BY webhook_delivery.id
+                LIMIT ?
+            ",
+            now,
+            BATCH_SIZE
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        for delivery in deliveries {
+            // Claim the delivery first, so nobody else posts it while we are waiting on the receiver
+            let claimed = sqlx::query!(
+                "UPDATE webhook_delivery SET next_attempt_at=? WHERE id=? AND status='pending' AND next_attempt_at <= ?",
+                now + CLAIM_TIMEOUT,
+                delivery.id,
+                now
+            )
+            .execute(self.get_db())
+            .await?
+            .rows_affected();
+            if claimed == 0 {
+                continue;
+            }
+
+            let result = post(
+                &self.http,
+                &delivery.url,
+                delivery.secret.as_deref(),
+                &delivery.payload,
+            )
+            .await;
+            let now = chrono::Utc::now().timestamp();
+            let attempts = delivery.attempts + 1;
+
+            match result {
+                Ok(()) => {
+                    sqlx::query!(
+                        "UPDATE webhook_delivery SET status='delivered', attempts=?, delivered_at=?, last_error=NULL WHERE id=?",
+                        attempts,
+                        now,
+                        delivery.id
+                    )
+                    .execute(self.get_db())
+                    .await?;
+                }
+                Err(e) if attempts >= MAX_ATTEMPTS => {
+                    warn!(
+                        "Giving up on webhook delivery {} after {} attempts: {}",
+                        delivery.id, attempts, e.message
+                    );
+                    sqlx::query!(
+                        "UPDATE webhook_delivery SET status='failed', attempts=?, last_error=? WHERE id=?",
+                        attempts,
+                        e.message,
+                        delivery.id
+                    )
+                    .execute(self.get_db())
+                    .await?;
+                }
+                Err(e) => {
+                    let backoff = e.retry_after.unwrap_or_else(|| {
+                        (BASE_BACKOFF << delivery.attempts.min(10)).min(MAX_BACKOFF)
+                    });
+                    sqlx::query!(
+                        "UPDATE webhook_delivery SET attempts=?, next_attempt_at=?, last_error=? WHERE id=?",
+                        attempts,
+                        now + backoff,
+                        e.message,
+                        delivery.id
+                    )
+                    .execute(self.get_db())
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1700000000, "{\"content\":\"hi\"}"),
+            "786cd668a72d88eb84d182ac85e334a27cf498b74f770467b2e0583cb80c4bcf"
+        );
+    }
+
+    #[test]
+    fn test_render_payload() {
+        let mut vars = HashMap::new();
+        vars.insert("message", "Waitlist \"HQ\" opened".to_string());
+        vars.insert("actor", "Some FC".to_string());
+
+        let default = render_payload(None, &vars);
+        assert_eq!(default, "{\"content\":\"Waitlist \\\"HQ\\\" opened\"}");
+
+        let templated = render_payload(Some("{\"text\": \"{actor}: {message}\"}"), &vars);
+        let value: serde_json::Value = serde_json::from_str(&templated).unwrap();
+        assert_eq!(value["text"], "Some FC: Waitlist \"HQ\" opened");
+
+        assert!(validate_template("{\"text\": \"{message}\"}").is_ok());
+        assert!(validate_template("{\"text\": {message}}").is_err());
+    }
+
+    // Accepts one request and answers it with the given status, handing back what it received
+    fn sink(status: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&received).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            match name.eq_ignore_ascii_case("content-length") {
+                                true => value.trim().parse::<usize>().ok(),
+                                false => None,
+                            }
+                        })
+                        .unwrap_or(0);
+                    if received.len() >= header_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            )
+            .unwrap();
+            String::from_utf8_lossy(&received).to_string()
+        });
+        (url, handle)
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        rocket::tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_post_to_sink() {
+        let (url, handle) = sink("204 No Content");
+        let body = "{\"content\":\"hi\"}";
+        let result = block_on(post(&reqwest::Client::new(), &url, Some("secret"), body));
+        assert!(result.is_ok());
+
+        let received = handle.join().unwrap().to_lowercase();
+        assert!(received.starts_with("post /hook"));
+        assert!(received.contains("x-waitlist-signature: sha256="));
+        assert!(received.ends_with(body));
+    }
+
+    #[test]
+    fn test_post_rate_limited() {
+        let (url, handle) = sink("429 Too Many Requests\r\nretry-after: 2.5");
+        let result = block_on(post(&reqwest::Client::new(), &url, None, "{}"));
+        handle.join().unwrap();
+
+        let error = result.unwrap_err();
+        assert_eq!(error.retry_after, Some(3));
+    }
+}
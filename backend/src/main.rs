@@ -58,6 +58,18 @@ async fn main() {
         .unwrap();
     let database = Arc::new(database);
 
+    let outbox_dispatcher = core::outbox::OutboxDispatcher::new(database.clone(), config.clone());
+    outbox_dispatcher.start();
+
+    let webhook_dispatcher = core::webhook::WebhookDispatcher::new(database.clone());
+    webhook_dispatcher.start();
+
+    let waitlist_scheduler = core::waitlist_scheduler::WaitlistScheduler::new(database.clone());
+    waitlist_scheduler.start();
+
+    let announcement_publisher = core::announcement::AnnouncementPublisher::new(database.clone());
+    announcement_publisher.start();
+
     if config.fleet_updater.enable {
         let fleet_updater =
             core::fleet_updater::FleetUpdater::new(database.clone(), config.clone());
@@ -70,6 +82,11 @@ async fn main() {
         skill_updater.start();
     }
 
+    if config.role_updater.enable {
+        let role_updater = core::role_updater::RoleUpdater::new(database.clone(), config.clone());
+        role_updater.start();
+    }
+
     let application = app::new(database, config);
     rocket::build()
         .register("/", catchers![not_authorized, forbidden, not_found])
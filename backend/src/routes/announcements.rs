@@ -1,18 +1,24 @@
 use crate::{
     app::Application,
-    core::{auth::AuthenticatedAccount, sse::Event},
+    core::{
+        announcement::{self, Targets},
+        audit,
+        auth::AuthenticatedAccount,
+    },
     util::{madness::Madness, types::Character},
 };
 
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Deserialize, Serialize)]
 struct Announcement {
     id: i64,
     message: String,
     is_alert: bool,
-    pages: Option<String>,
+    starts_at: i64,
+    ends_at: Option<i64>,
     created_by_id: i64,
     created_at: i64,
     revoked_by_id: Option<i64>,
@@ -23,7 +29,11 @@ struct Announcement {
 struct RequestPayload {
     message: String,
     is_alert: bool,
-    pages: Option<String>,
+    #[serde(default)]
+    targets: Targets,
+    // Announcements start right away and run until revoked unless told otherwise
+    starts_at: Option<i64>,
+    ends_at: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -31,8 +41,10 @@ struct AnnouncementPayload {
     id: i64,
     message: String,
     is_alert: bool,
+    targets: Targets,
+    starts_at: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pages: Option<String>,
+    ends_at: Option<i64>,
     created_by: Option<Character>,
     created_at: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -41,14 +53,22 @@ struct AnnouncementPayload {
     revoked_at: Option<i64>,
 }
 
-async fn get_active_announcements(app: &Application) -> Result<Vec<AnnouncementPayload>, Madness> {
+// Live announcements the viewer may see, or with `include_scheduled` also the ones that have yet
+// to start, for the FC page.
+async fn get_announcements(
+    conn: &mut sqlx::MySqlConnection,
+    account: Option<&AuthenticatedAccount>,
+    include_scheduled: bool,
+) -> Result<Vec<AnnouncementPayload>, Madness> {
+    let now = chrono::Utc::now().timestamp();
     let announcements: Vec<Announcement> = sqlx::query_as!(
         Announcement,
         "SELECT
         id,
         message,
         is_alert AS `is_alert!: bool`,
-        pages,
+        starts_at,
+        ends_at,
         created_by_id,
         created_at,
         revoked_by_id,
@@ -56,27 +76,40 @@ async fn get_active_announcements(app: &Application) -> Result<Vec<AnnouncementP
       FROM
         announcement
       WHERE
-        revoked_at IS NULL"
+        revoked_at IS NULL AND (ends_at IS NULL OR ends_at > ?) AND (? OR starts_at <= ?)
+      ORDER BY
+        starts_at",
+        now,
+        include_scheduled,
+        now
     )
-    .fetch_all(app.get_db())
+    .fetch_all(&mut *conn)
     .await?;
 
+    let mut targets = announcement::load_targets(&mut *conn).await?;
     let mut payloads = Vec::new();
 
     for a in announcements {
+        let targets = targets.remove(&a.id).unwrap_or_default();
+        if !include_scheduled && !targets.visible_to(account) {
+            continue;
+        }
+
         let created_by = sqlx::query_as!(
             Character,
             "SELECT * FROM `character` WHERE id=?",
             a.created_by_id
         )
-        .fetch_optional(app.get_db())
+        .fetch_optional(&mut *conn)
         .await?;
 
         payloads.push(AnnouncementPayload {
             id: a.id,
             message: a.message,
             is_alert: a.is_alert,
-            pages: a.pages,
+            targets,
+            starts_at: a.starts_at,
+            ends_at: a.ends_at,
             created_by,
             created_at: a.created_at,
             revoked_by: None,
@@ -87,9 +120,37 @@ async fn get_active_announcements(app: &Application) -> Result<Vec<AnnouncementP
     Ok(payloads)
 }
 
-#[get("/api/v2/announcements")]
-async fn list(app: &rocket::State<Application>) -> Result<Json<Vec<AnnouncementPayload>>, Madness> {
-    let payloads = get_active_announcements(app).await?;
+fn validate(body: &RequestPayload, now: i64) -> Result<(i64, Option<i64>), Madness> {
+    body.targets.validate().map_err(Madness::BadRequest)?;
+
+    let starts_at = body.starts_at.unwrap_or(now);
+    if let Some(ends_at) = body.ends_at {
+        if ends_at <= starts_at || ends_at <= now {
+            return Err(Madness::BadRequest(
+                "An announcement has to end after it starts, and in the future".to_string(),
+            ));
+        }
+    }
+
+    Ok((starts_at, body.ends_at))
+}
+
+#[get("/api/v2/announcements?<all>")]
+async fn list(
+    account: Option<AuthenticatedAccount>,
+    app: &rocket::State<Application>,
+    all: Option<bool>,
+) -> Result<Json<Vec<AnnouncementPayload>>, Madness> {
+    let include_scheduled = all.unwrap_or(false);
+    if include_scheduled {
+        match &account {
+            Some(account) => account.require_access("waitlist-tag:HQ-FC")?,
+            None => return Err(Madness::AccessDenied),
+        }
+    }
+
+    let mut conn = app.get_db().acquire().await?;
+    let payloads = get_announcements(&mut conn, account.as_ref(), include_scheduled).await?;
     return Ok(Json(payloads));
 }
 
@@ -102,28 +163,54 @@ async fn create(
     account.require_access("waitlist-tag:HQ-FC")?;
 
     let now = chrono::Utc::now().timestamp();
+    let (starts_at, ends_at) = validate(&body, now)?;
+    let live = starts_at <= now;
+    let published_at = if live { Some(now) } else { None };
 
-    sqlx::query!(
-        "INSERT INTO announcement (message, is_alert, pages, created_by_id, created_at) VALUES (?, ?, ?, ?, ?)",
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "INSERT INTO announcement (message, is_alert, starts_at, ends_at, published_at, created_by_id, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
         body.message,
         body.is_alert,
-        body.pages,
+        starts_at,
+        ends_at,
+        published_at,
         account.id,
         now
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
     .await?;
+    let announcement_id = crate::last_insert_id!(result);
+    announcement::save_targets(&mut tx, announcement_id, &body.targets).await?;
 
-    // Send an updated array of announcements to users active on the site
-    let payloads = get_active_announcements(app).await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "announcement.create",
+        None,
+        &json!({
+            "announcement_id": announcement_id,
+            "message": body.message,
+            "is_alert": body.is_alert,
+            "targets": body.targets,
+            "starts_at": starts_at,
+            "ends_at": ends_at,
+        }),
+    )
+    .await?;
 
-    app.sse_client
-        .submit(vec![Event::new_json(
-            "announcments",
-            "announcment;new",
-            &payloads,
-        )])
+    // Scheduled announcements are published by the announcement publisher once they start
+    if live {
+        announcement::went_live(
+            &mut tx,
+            announcement_id,
+            account.id,
+            &body.message,
+            body.is_alert,
+        )
         .await?;
+    }
+    tx.commit().await?;
 
     return Ok("Ok");
 }
@@ -137,53 +224,64 @@ async fn update(
 ) -> Result<&'static str, Madness> {
     account.require_access("waitlist-tag:HQ-FC")?;
 
-    let announcement: Option<Announcement> = sqlx::query_as!(
-        Announcement,
-        "SELECT
-          id,
-          message,
-          is_alert AS `is_alert!: bool`,
-          pages,
-          created_by_id,
-          created_at,
-          revoked_by_id,
-          revoked_at
-        FROM
-          announcement
-        WHERE
-          id=? AND revoked_at IS NULL",
+    let now = chrono::Utc::now().timestamp();
+    let (starts_at, ends_at) = validate(&body, now)?;
+
+    let announcement = sqlx::query!(
+        "SELECT published_at FROM announcement WHERE id=? AND revoked_at IS NULL",
         announcement_id
     )
     .fetch_optional(app.get_db())
     .await?;
 
-    if announcement.is_none() {
-        return Err(Madness::BadRequest(format!(
-            "Announcment could not be found."
-        )));
-    }
-
+    let announcement = match announcement {
+        Some(announcement) => announcement,
+        None => {
+            return Err(Madness::BadRequest(format!(
+                "Announcment could not be found."
+            )))
+        }
+    };
+
+    // Moving the start into the future takes an announcement down until then
+    let published_at = match starts_at <= now {
+        true => Some(announcement.published_at.unwrap_or(now)),
+        false => None,
+    };
+
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
-        "UPDATE announcement SET message=?, is_alert=?, pages=?, created_by_id=? WHERE id=?",
+        "UPDATE announcement SET message=?, is_alert=?, starts_at=?, ends_at=?, published_at=?, expired_at=NULL, created_by_id=? WHERE id=?",
         body.message,
         body.is_alert,
-        body.pages,
+        starts_at,
+        ends_at,
+        published_at,
         account.id,
         announcement_id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
     .await?;
+    announcement::save_targets(&mut tx, announcement_id, &body.targets).await?;
 
-    // Send an updated array of announcements to users active on the site
-    let payloads = get_active_announcements(app).await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "announcement.update",
+        None,
+        &json!({
+            "announcement_id": announcement_id,
+            "message": body.message,
+            "is_alert": body.is_alert,
+            "targets": body.targets,
+            "starts_at": starts_at,
+            "ends_at": ends_at,
+        }),
+    )
+    .await?;
 
-    app.sse_client
-        .submit(vec![Event::new_json(
-            "announcments",
-            "announcment;updated",
-            &payloads,
-        )])
-        .await?;
+    announcement::notify(&mut tx, "announcment;updated", announcement_id).await?;
+    tx.commit().await?;
 
     return Ok("Ok");
 }
@@ -196,21 +294,8 @@ async fn revoke(
 ) -> Result<&'static str, Madness> {
     account.require_access("waitlist-tag:HQ-FC")?;
 
-    let announcement: Option<Announcement> = sqlx::query_as!(
-        Announcement,
-        "SELECT
-          id,
-          message,
-          is_alert AS `is_alert!: bool`,
-          pages,
-          created_by_id,
-          created_at,
-          revoked_by_id,
-          revoked_at
-        FROM
-          announcement
-        WHERE
-          id=? AND revoked_at IS NULL",
+    let announcement = sqlx::query!(
+        "SELECT id FROM announcement WHERE id=? AND revoked_at IS NULL",
         announcement_id
     )
     .fetch_optional(app.get_db())
@@ -224,25 +309,27 @@ async fn revoke(
 
     let now = chrono::Utc::now().timestamp();
 
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
         "UPDATE announcement SET revoked_by_id=?, revoked_at=? WHERE id=?",
         account.id,
         now,
         announcement_id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
     .await?;
 
-    // Send an updated array of announcements to users active on the site
-    let payloads = get_active_announcements(app).await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "announcement.revoke",
+        None,
+        &json!({ "announcement_id": announcement_id }),
+    )
+    .await?;
 
-    app.sse_client
-        .submit(vec![Event::new_json(
-            "announcments",
-            "announcment;updated",
-            &payloads,
-        )])
-        .await?;
+    announcement::notify(&mut tx, "announcment;updated", announcement_id).await?;
+    tx.commit().await?;
 
     return Ok("Ok");
 }
@@ -0,0 +1,140 @@
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Application,
+    core::auth::{create_api_token, split_token_scopes, AuthenticatedAccount},
+    util::madness::Madness,
+};
+
+#[derive(Debug, Serialize)]
+struct ApiTokenEntry {
+    id: i64,
+    name: String,
+    scopes: Vec<String>,
+    created_at: i64,
+    last_used_at: Option<i64>,
+    revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateTokenRequest {
+    name: String,
+    scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTokenResponse {
+    id: i64,
+    token: String,
+}
+
+#[get("/api/tokens")]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<ApiTokenEntry>>, Madness> {
+    account.require_access("api-tokens-manage")?;
+
+    let tokens = sqlx::query!(
+        "SELECT id, name, scopes, created_at, last_used_at, revoked_at FROM api_token WHERE account_id=? ORDER BY id DESC",
+        account.id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|token| ApiTokenEntry {
+        id: token.id,
+        name: token.name,
+        scopes: split_token_scopes(&token.scopes)
+            .map(|s| s.to_string())
+            .collect(),
+        created_at: token.created_at,
+        last_used_at: token.last_used_at,
+        revoked_at: token.revoked_at,
+    })
+    .collect();
+
+    Ok(Json(tokens))
+}
+
+#[post("/api/tokens", data = "<input>")]
+async fn create(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<CreateTokenRequest>,
+) -> Result<Json<CreateTokenResponse>, Madness> {
+    account.require_access("api-tokens-manage")?;
+
+    if input.name.is_empty() || input.name.len() > 64 {
+        return Err(Madness::BadRequest(
+            "Token name must be between 1 and 64 characters".to_string(),
+        ));
+    }
+    if input.scopes.is_empty() {
+        return Err(Madness::BadRequest(
+            "A token needs at least one scope".to_string(),
+        ));
+    }
+
+    // Tokens can only be limited to keys the account already holds
+    for scope in &input.scopes {
+        if !account.access.contains(scope) {
+            return Err(Madness::Forbidden(format!(
+                "You cannot grant the scope \"{}\" to a token",
+                scope
+            )));
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let scopes = input.scopes.join(" ");
+    let result = sqlx::query!(
+        "INSERT INTO api_token (account_id, name, scopes, created_at) VALUES (?, ?, ?, ?)",
+        account.id,
+        input.name,
+        scopes,
+        now
+    )
+    .execute(app.get_db())
+    .await?;
+    let id = crate::last_insert_id!(result);
+
+    Ok(Json(CreateTokenResponse {
+        id,
+        token: create_api_token(app, id),
+    }))
+}
+
+#[delete("/api/tokens/<token_id>")]
+async fn revoke(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    token_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("api-tokens-manage")?;
+
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query!(
+        "UPDATE api_token SET revoked_at=? WHERE id=? AND account_id=? AND revoked_at IS NULL",
+        now,
+        token_id,
+        account.id
+    )
+    .execute(app.get_db())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Madness::NotFound("Token not found or already revoked"));
+    }
+
+    Ok("Ok")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,   // GET      /api/tokens
+        create, // POST     /api/tokens
+        revoke, // DELETE   /api/tokens/<token_id>
+    ]
+}
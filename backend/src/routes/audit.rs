@@ -0,0 +1,107 @@
+use rocket::serde::json::{Json, Value};
+use serde::Serialize;
+
+use crate::{
+    app::Application,
+    core::auth::AuthenticatedAccount,
+    util::{madness::Madness, types::Character},
+};
+
+const PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Serialize)]
+struct AuditLogEntry {
+    id: i64,
+    actor: Character,
+    action: String,
+    target_id: Option<i64>,
+    target_name: Option<String>,
+    payload: Value,
+    logged_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditLogResponse {
+    entries: Vec<AuditLogEntry>,
+    next: Option<i64>,
+}
+
+#[get("/api/audit?<actor_id>&<target_id>&<action>&<before>")]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    actor_id: Option<i64>,
+    target_id: Option<i64>,
+    action: Option<String>,
+    before: Option<i64>,
+) -> Result<Json<AuditLogResponse>, Madness> {
+    account.require_access("audit-view")?;
+
+    // Action filters match on prefix, so "waitlist." returns every waitlist action
+    let action_like = action.map(|a| format!("{}%", a.replace('%', "")));
+
+    let rows = sqlx::query!(
+        "
+            SELECT
+                audit_log.id,
+                actor.id actor_id,
+                actor.name actor_name,
+                action,
+                target_id,
+                target.name `target_name?`,
+                payload,
+                logged_at
+            FROM audit_log
+            JOIN `character` actor ON actor.id = audit_log.actor_id
+            LEFT JOIN `character` target ON target.id = audit_log.target_id
+            WHERE
+                (? IS NULL OR audit_log.actor_id = ?)
+                AND (? IS NULL OR audit_log.target_id = ?)
+                AND (? IS NULL OR action LIKE ?)
+                AND (? IS NULL OR audit_log.id < ?)
+            ORDER BY audit_log.id DESC
+            LIMIT ?
+        ",
+        actor_id,
+        actor_id,
+        target_id,
+        target_id,
+        action_like,
+        action_like,
+        before,
+        before,
+        PAGE_SIZE
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let next = match rows.len() as i64 == PAGE_SIZE {
+        true => rows.last().map(|r| r.id),
+        false => None,
+    };
+
+    let entries = rows
+        .into_iter()
+        .map(|row| AuditLogEntry {
+            id: row.id,
+            actor: Character {
+                id: row.actor_id,
+                name: row.actor_name,
+                corporation_id: None,
+            },
+            action: row.action,
+            target_id: row.target_id,
+            target_name: row.target_name,
+            payload: rocket::serde::json::from_str(&row.payload).unwrap_or(Value::Null),
+            logged_at: row.logged_at,
+        })
+        .collect();
+
+    Ok(Json(AuditLogResponse { entries, next }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list, // GET      /api/audit
+    ]
+}
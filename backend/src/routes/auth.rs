@@ -2,15 +2,19 @@ use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
 use crate::app;
-use crate::core::auth::{AuthenticatedAccount, AuthenticationError, CookieSetter};
+use crate::core::auth::{
+    get_role, AuthenticatedAccount, AuthenticationError, CookieSetter, Impersonation,
+};
 use crate::core::esi::ESIScope;
 use crate::util::{madness::Madness, types};
 
 #[derive(Serialize)]
 struct WhoamiResponse {
     account_id: i64,
-    access: Vec<&'static str>,
+    access: Vec<String>,
     characters: Vec<types::Character>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    impersonation: Option<Impersonation>,
 }
 
 #[get("/api/auth/whoami")]
@@ -42,15 +46,13 @@ async fn whoami(
         });
     }
 
-    let mut access_levels = Vec::new();
-    for key in account.access {
-        access_levels.push(key.as_str());
-    }
+    let access_levels = account.access.into_iter().collect();
 
     Ok(Json(WhoamiResponse {
         account_id: account.id,
         access: access_levels,
         characters,
+        impersonation: account.impersonation,
     }))
 }
 
@@ -59,7 +61,8 @@ async fn logout<'r>(
     app: &rocket::State<app::Application>,
     account: Option<AuthenticatedAccount>,
 ) -> Result<CookieSetter, Madness> {
-    if let Some(account) = account {
+    // Logging out while impersonating must not touch the impersonated account
+    if let Some(account) = account.filter(|a| a.impersonation.is_none()) {
         sqlx::query!(
             "DELETE FROM alt_character WHERE account_id = ? OR alt_id = ?",
             account.id,
@@ -127,6 +130,11 @@ async fn callback(
     let account = match account_raw {
         Err(AuthenticationError::MissingCookie) => None,
         Err(AuthenticationError::InvalidToken) => None,
+        Err(AuthenticationError::ReadOnly) => {
+            return Err(Madness::Forbidden(
+                "Stop impersonating before logging in again".to_string(),
+            ))
+        }
         Err(AuthenticationError::DatabaseError(e)) => return Err(e.into()),
         Ok(acc) => Some(acc),
     };
@@ -162,12 +170,7 @@ async fn callback(
         if input.state.is_some() && input.state.unwrap() == "alt" && account.is_some() {
             let account = account.unwrap();
             if account.id != character_id {
-                let is_admin = sqlx::query!(
-                    "SELECT character_id FROM admin WHERE character_id = ?",
-                    character_id
-                )
-                .fetch_optional(app.get_db())
-                .await?;
+                let is_admin = get_role(app.get_db(), character_id).await?;
 
                 if is_admin.is_some() {
                     return Err(Madness::BadRequest(
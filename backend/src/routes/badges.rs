@@ -1,7 +1,12 @@
-use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+use crate::{
+    app::Application,
+    core::{audit, auth::AuthenticatedAccount},
+    util::madness::Madness,
+};
 
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Debug, Serialize)]
 struct Badge {
@@ -222,6 +227,7 @@ async fn assign_badge(
     }
 
     let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
         "INSERT INTO badge_assignment (characterId, badgeId, grantedById, grantedAt) VALUES (?, ?, ?, ?)",
         character.id,
@@ -229,9 +235,19 @@ async fn assign_badge(
         account.id,
         now
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
     .await?;
 
+    audit::log(
+        &mut tx,
+        account.id,
+        "badge.assign",
+        Some(character.id),
+        &json!({ "badge_id": badge_id, "badge": badge.name }),
+    )
+    .await?;
+    tx.commit().await?;
+
     Ok("Ok")
 }
 
@@ -245,14 +261,27 @@ async fn revoke_badge(
 ) -> Result<&'static str, Madness> {
     account.require_access("badges-manage")?;
 
-    sqlx::query!(
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
         "DELETE FROM badge_assignment WHERE characterId=? AND badgeId=?",
         character_id,
         badge_id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
     .await?;
 
+    if result.rows_affected() > 0 {
+        audit::log(
+            &mut tx,
+            account.id,
+            "badge.revoke",
+            Some(character_id),
+            &json!({ "badge_id": badge_id }),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
     Ok("Ok")
 }
 
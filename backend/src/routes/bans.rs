@@ -1,6 +1,6 @@
 use crate::{
     app::Application,
-    core::auth::AuthenticatedAccount,
+    core::{audit, auth::AuthenticatedAccount, webhook},
     util::{
         madness::Madness,
         types::{Ban, Character, Entity},
@@ -9,6 +9,7 @@ use crate::{
 
 use rocket::serde::json::Json;
 use serde::Deserialize;
+use serde_json::json;
 use sqlx::types::chrono::Utc;
 
 #[derive(Deserialize)]
@@ -108,7 +109,8 @@ async fn create(
         }
     };
 
-    sqlx::query!(
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
         "INSERT INTO ban (entity_type, entity_id, entity_name, issued_at, issued_by, reason, public_reason, revoked_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
         e.category,
         e.id,
@@ -119,9 +121,40 @@ async fn create(
         req_body.public_reason,
         expires_at,
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "ban.create",
+        Some(e.id),
+        &json!({
+            "ban_id": crate::last_insert_id!(result),
+            "entity_type": e.category,
+            "entity_name": esi_res.name,
+            "reason": req_body.reason,
+            "public_reason": req_body.public_reason,
+            "expires_at": expires_at,
+        }),
+    )
+    .await?;
+
+    webhook::emit(
+        &mut tx,
+        "ban.issue",
+        account.id,
+        format!("{} {} was banned", e.category, esi_res.name),
+        vec![
+            ("entity_type", e.category.clone()),
+            ("entity_name", esi_res.name.clone()),
+            ("reason", req_body.public_reason.clone().unwrap_or_default()),
+        ],
+    )
     .await?;
 
+    tx.commit().await?;
+
     Ok("Ok")
 }
 
@@ -151,7 +184,7 @@ async fn update(
 
     let now = Utc::now().timestamp();
 
-    if let None = sqlx::query!(
+    let ban = match sqlx::query!(
         "SELECT * FROM ban WHERE id=? AND (revoked_at IS NULL OR revoked_at > ?)",
         ban_id,
         now
@@ -159,10 +192,13 @@ async fn update(
     .fetch_optional(app.get_db())
     .await?
     {
-        return Err(Madness::BadRequest(format!(
-            "Cannot revoke invalid ban. It is either invalid or doesn't exist"
-        )));
-    }
+        Some(ban) => ban,
+        None => {
+            return Err(Madness::BadRequest(format!(
+                "Cannot revoke invalid ban. It is either invalid or doesn't exist"
+            )))
+        }
+    };
 
     let expires_at = match req_body.revoked_at.as_ref() {
         None => None,
@@ -172,6 +208,7 @@ async fn update(
         }
     };
 
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
         "UPDATE
             ban
@@ -190,7 +227,21 @@ async fn update(
         now,
         ban_id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "ban.update",
+        Some(ban.entity_id),
+        &json!({
+            "ban_id": ban_id,
+            "reason": req_body.reason,
+            "public_reason": req_body.public_reason,
+            "expires_at": expires_at,
+        }),
+    )
     .await?;
 
     Ok("Ok")
@@ -227,14 +278,25 @@ async fn revoke(
             )));
         }
 
+        let mut tx = app.get_db().begin().await?;
         sqlx::query!(
             "UPDATE ban SET revoked_at=?, revoked_by=? WHERE id=?",
             now,
             account.id,
             ban_id
         )
-        .execute(app.get_db())
+        .execute(&mut tx)
+        .await?;
+
+        audit::log(
+            &mut tx,
+            account.id,
+            "ban.revoke",
+            Some(ban.entity_id),
+            &json!({ "ban_id": ban_id }),
+        )
         .await?;
+        tx.commit().await?;
 
         return Ok("Ok");
     }
@@ -0,0 +1,558 @@
+use std::collections::HashMap;
+
+use rocket::http::ContentType;
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    app::Application,
+    core::{audit, auth::AuthenticatedAccount},
+    util::{ical, madness::Madness, types::Character},
+};
+
+const DEFAULT_RANGE: i64 = 30 * 86400;
+const MAX_RANGE: i64 = 92 * 86400;
+// Fleets stay on the calendar for a while after they ended
+const PAST_GRACE: i64 = 6 * 3600;
+// How far from now a planned fleet may start or end
+const MAX_PAST: i64 = 30 * 86400;
+const MAX_FUTURE: i64 = 365 * 86400;
+
+#[derive(Debug, Serialize)]
+struct WaitlistRef {
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedFleet {
+    id: i64,
+    title: String,
+    waitlist: WaitlistRef,
+    doctrine: String,
+    description: Option<String>,
+    starts_at: i64,
+    ends_at: i64,
+    opens_waitlist: bool,
+    fc: Option<Character>,
+    backups: Vec<Character>,
+}
+
+async fn load_planned(db: &crate::DB, from: i64, to: i64) -> Result<Vec<PlannedFleet>, Madness> {
+    let mut signups: HashMap<i64, Vec<(String, Character)>> = HashMap::new();
+    for signup in sqlx::query!(
+        "
+            SELECT planned_fleet_id, character_id, `character`.name, role FROM planned_fleet_signup
+            JOIN planned_fleet ON planned_fleet.id = planned_fleet_id
+            JOIN `character` ON `character`.id = character_id
+            WHERE ends_at > ? AND starts_at < ?
+            ORDER BY signed_up_at
+        ",
+        from,
+        to
+    )
+    .fetch_all(db)
+    .await?
+    {
+        signups.entry(signup.planned_fleet_id).or_default().push((
+            signup.role,
+            Character {
+                id: signup.character_id,
+                name: signup.name,
+                corporation_id: None,
+            },
+        ));
+    }
+
+    Ok(sqlx::query!(
+        "
+            SELECT
+                planned_fleet.id, title, waitlist_id, waitlist.name AS waitlist_name, doctrine,
+                description, starts_at, ends_at, schedule_id
+            FROM planned_fleet
+            JOIN waitlist ON waitlist.id = waitlist_id
+            WHERE ends_at > ? AND starts_at < ?
+            ORDER BY starts_at
+        ",
+        from,
+        to
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|fleet| {
+        let mut fc = None;
+        let mut backups = Vec::new();
+        for (role, character) in signups.remove(&fleet.id).unwrap_or_default() {
+            match role.as_str() {
+                "fc" => fc = Some(character),
+                _ => backups.push(character),
+            }
+        }
+
+        PlannedFleet {
+            id: fleet.id,
+            title: fleet.title,
+            waitlist: WaitlistRef {
+                id: fleet.waitlist_id,
+                name: fleet.waitlist_name,
+            },
+            doctrine: fleet.doctrine,
+            description: fleet.description,
+            starts_at: fleet.starts_at,
+            ends_at: fleet.ends_at,
+            opens_waitlist: fleet.schedule_id.is_some(),
+            fc,
+            backups,
+        }
+    })
+    .collect())
+}
+
+// The calendar is public, so anyone can see what is planned without logging in
+#[get("/api/calendar?<from>&<to>")]
+async fn list(
+    app: &rocket::State<Application>,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<Json<Vec<PlannedFleet>>, Madness> {
+    let invalid = || Madness::BadRequest("Invalid calendar range".to_string());
+    let from = from.unwrap_or_else(|| chrono::Utc::now().timestamp() - PAST_GRACE);
+    let to = match to {
+        Some(to) => to,
+        None => from.checked_add(DEFAULT_RANGE).ok_or_else(invalid)?,
+    };
+    match to.checked_sub(from) {
+        Some(range) if range > 0 && range <= MAX_RANGE => (),
+        _ => return Err(invalid()),
+    }
+
+    Ok(Json(load_planned(app.get_db(), from, to).await?))
+}
+
+#[get("/api/calendar.ics")]
+async fn export(app: &rocket::State<Application>) -> Result<(ContentType, String), Madness> {
+    let now = chrono::Utc::now().timestamp();
+    let fleets = load_planned(app.get_db(), now - PAST_GRACE, now + MAX_RANGE).await?;
+
+    let events: Vec<_> = fleets
+        .into_iter()
+        .map(|fleet| {
+            let mut description = vec![
+                format!("Doctrine: {}", fleet.doctrine),
+                format!("Waitlist: {}", fleet.waitlist.name),
+                format!(
+                    "FC: {}",
+                    fleet.fc.as_ref().map_or("TBD", |fc| fc.name.as_str())
+                ),
+            ];
+            if !fleet.backups.is_empty() {
+                let names: Vec<_> = fleet.backups.iter().map(|c| c.name.as_str()).collect();
+                description.push(format!("Backup: {}", names.join(", ")));
+            }
+            if let Some(text) = fleet.description {
+                description.push(String::new());
+                description.push(text);
+            }
+
+            ical::Event {
+                uid: format!("planned-fleet-{}@waitlist", fleet.id),
+                stamp: now,
+                start: fleet.starts_at,
+                end: fleet.ends_at,
+                summary: fleet.title,
+                description: description.join("\n"),
+            }
+        })
+        .collect();
+
+    Ok((
+        ContentType::new("text", "calendar"),
+        ical::calendar("Fleets", &events),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct PlannedFleetRequest {
+    title: String,
+    waitlist_id: i64,
+    doctrine: String,
+    description: Option<String>,
+    starts_at: i64,
+    ends_at: i64,
+    // Opens the waitlist when the fleet starts and closes it when it ends
+    open_waitlist: bool,
+    empty_on_close: bool,
+}
+
+fn validate(input: &PlannedFleetRequest) -> Result<(), Madness> {
+    if input.title.is_empty() || input.title.len() > 255 {
+        return Err(Madness::BadRequest(
+            "Title must be between 1 and 255 characters".to_string(),
+        ));
+    }
+    if input.doctrine.is_empty() || input.doctrine.len() > 64 {
+        return Err(Madness::BadRequest(
+            "Doctrine must be between 1 and 64 characters".to_string(),
+        ));
+    }
+    if input.ends_at <= input.starts_at {
+        return Err(Madness::BadRequest(
+            "A fleet has to start before it ends".to_string(),
+        ));
+    }
+    let now = chrono::Utc::now().timestamp();
+    if input.ends_at <= now {
+        return Err(Madness::BadRequest(
+            "A fleet has to end in the future".to_string(),
+        ));
+    }
+    if input.starts_at < now - MAX_PAST || input.ends_at > now + MAX_FUTURE {
+        return Err(Madness::BadRequest(
+            "A fleet has to take place within the next year".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_waitlist(db: &crate::DB, waitlist_id: i64) -> Result<(), Madness> {
+    match sqlx::query!("SELECT id FROM waitlist WHERE id=?", waitlist_id)
+        .fetch_optional(db)
+        .await?
+    {
+        Some(_) => Ok(()),
+        None => Err(Madness::NotFound("Waitlist not found")),
+    }
+}
+
+// Keeps the waitlist schedule behind a planned fleet in line with it. Once the schedule has opened
+// the waitlist, only its end can still be moved.
+async fn save_schedule(
+    tx: &mut crate::DBTX<'_>,
+    account_id: i64,
+    input: &PlannedFleetRequest,
+    schedule_id: Option<i64>,
+) -> Result<Option<i64>, Madness> {
+    let existing = match schedule_id {
+        Some(id) => sqlx::query!("SELECT id, is_opened FROM waitlist_schedule WHERE id=?", id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|schedule| (schedule.id, schedule.is_opened > 0)),
+        None => None,
+    };
+
+    match (existing, input.open_waitlist) {
+        (Some((id, _)), false) => {
+            sqlx::query!("DELETE FROM waitlist_schedule WHERE id=?", id)
+                .execute(&mut *tx)
+                .await?;
+            Ok(None)
+        }
+        (Some((id, true)), true) => {
+            sqlx::query!(
+                "UPDATE waitlist_schedule SET closes_at=?, empty_on_close=? WHERE id=?",
+                input.ends_at,
+                input.empty_on_close,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            Ok(Some(id))
+        }
+        (Some((id, false)), true) => {
+            sqlx::query!(
+                "UPDATE waitlist_schedule SET waitlist_id=?, opens_at=?, closes_at=?, empty_on_close=? WHERE id=?",
+                input.waitlist_id,
+                input.starts_at,
+                input.ends_at,
+                input.empty_on_close,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            Ok(Some(id))
+        }
+        (None, true) => {
+            let now = chrono::Utc::now().timestamp();
+            let result = sqlx::query!(
+                "
+                    INSERT INTO waitlist_schedule
+                        (waitlist_id, opens_at, closes_at, repeat_every, empty_on_close, created_by_id, created_at)
+                    VALUES (?, ?, ?, NULL, ?, ?, ?)
+                ",
+                input.waitlist_id,
+                input.starts_at,
+                input.ends_at,
+                input.empty_on_close,
+                account_id,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+            Ok(Some(crate::last_insert_id!(result)))
+        }
+        (None, false) => Ok(None),
+    }
+}
+
+#[post("/api/calendar", data = "<input>")]
+async fn create(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<PlannedFleetRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    validate(&input)?;
+
+    check_waitlist(app.get_db(), input.waitlist_id).await?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
+    let schedule_id = save_schedule(&mut tx, account.id, &input, None).await?;
+    let result = sqlx::query!(
+        "
+            INSERT INTO planned_fleet
+                (title, waitlist_id, doctrine, description, starts_at, ends_at, schedule_id, created_by_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ",
+        input.title,
+        input.waitlist_id,
+        input.doctrine,
+        input.description,
+        input.starts_at,
+        input.ends_at,
+        schedule_id,
+        account.id,
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "calendar.create",
+        None,
+        &json!({
+            "planned_fleet_id": crate::last_insert_id!(result),
+            "title": input.title,
+            "waitlist_id": input.waitlist_id,
+            "starts_at": input.starts_at,
+            "ends_at": input.ends_at,
+            "schedule_id": schedule_id,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[put("/api/calendar/<planned_fleet_id>", data = "<input>")]
+async fn update(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    planned_fleet_id: i64,
+    input: Json<PlannedFleetRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    validate(&input)?;
+    check_waitlist(app.get_db(), input.waitlist_id).await?;
+
+    let mut tx = app.get_db().begin().await?;
+    let fleet = match sqlx::query!(
+        "SELECT schedule_id FROM planned_fleet WHERE id=? FOR UPDATE",
+        planned_fleet_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    {
+        Some(fleet) => fleet,
+        None => return Err(Madness::NotFound("Planned fleet not found")),
+    };
+
+    let schedule_id = save_schedule(&mut tx, account.id, &input, fleet.schedule_id).await?;
+    sqlx::query!(
+        "
+            UPDATE planned_fleet
+            SET title=?, waitlist_id=?, doctrine=?, description=?, starts_at=?, ends_at=?, schedule_id=?
+            WHERE id=?
+        ",
+        input.title,
+        input.waitlist_id,
+        input.doctrine,
+        input.description,
+        input.starts_at,
+        input.ends_at,
+        schedule_id,
+        planned_fleet_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "calendar.update",
+        None,
+        &json!({
+            "planned_fleet_id": planned_fleet_id,
+            "title": input.title,
+            "waitlist_id": input.waitlist_id,
+            "starts_at": input.starts_at,
+            "ends_at": input.ends_at,
+            "schedule_id": schedule_id,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/calendar/<planned_fleet_id>")]
+async fn delete(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    planned_fleet_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+
+    let mut tx = app.get_db().begin().await?;
+    let fleet = match sqlx::query!(
+        "SELECT title, schedule_id FROM planned_fleet WHERE id=? FOR UPDATE",
+        planned_fleet_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    {
+        Some(fleet) => fleet,
+        None => return Err(Madness::NotFound("Planned fleet not found")),
+    };
+
+    sqlx::query!("DELETE FROM planned_fleet WHERE id=?", planned_fleet_id)
+        .execute(&mut tx)
+        .await?;
+    // Like deleting the schedule directly, this leaves an already opened waitlist open
+    if let Some(schedule_id) = fleet.schedule_id {
+        sqlx::query!("DELETE FROM waitlist_schedule WHERE id=?", schedule_id)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "calendar.delete",
+        None,
+        &json!({ "planned_fleet_id": planned_fleet_id, "title": fleet.title }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[derive(Debug, Deserialize)]
+struct SignupRequest {
+    role: String,
+}
+
+#[post("/api/calendar/<planned_fleet_id>/signup", data = "<input>")]
+async fn signup(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    planned_fleet_id: i64,
+    input: Json<SignupRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+
+    if input.role != "fc" && input.role != "backup" {
+        return Err(Madness::BadRequest("Unknown sign-up role".to_string()));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
+    // Locking the fleet makes sure two people can't both take the FC spot
+    match sqlx::query!(
+        "SELECT ends_at FROM planned_fleet WHERE id=? FOR UPDATE",
+        planned_fleet_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    {
+        Some(fleet) if fleet.ends_at > now => (),
+        Some(_) => {
+            return Err(Madness::BadRequest(
+                "This fleet is already over".to_string(),
+            ))
+        }
+        None => return Err(Madness::NotFound("Planned fleet not found")),
+    };
+
+    if input.role == "fc" {
+        if let Some(fc) = sqlx::query!(
+            "
+                SELECT `character`.name FROM planned_fleet_signup
+                JOIN `character` ON `character`.id = character_id
+                WHERE planned_fleet_id=? AND role='fc' AND character_id != ?
+            ",
+            planned_fleet_id,
+            account.id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            return Err(Madness::BadRequest(format!(
+                "{} is already signed up as FC",
+                fc.name
+            )));
+        }
+    }
+
+    sqlx::query!(
+        "REPLACE INTO planned_fleet_signup (planned_fleet_id, character_id, role, signed_up_at) VALUES (?, ?, ?, ?)",
+        planned_fleet_id,
+        account.id,
+        input.role,
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/calendar/<planned_fleet_id>/signup")]
+async fn withdraw(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    planned_fleet_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+
+    sqlx::query!(
+        "DELETE FROM planned_fleet_signup WHERE planned_fleet_id=? AND character_id=?",
+        planned_fleet_id,
+        account.id
+    )
+    .execute(app.get_db())
+    .await?;
+
+    Ok("OK")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,     // GET      /api/calendar
+        export,   // GET      /api/calendar.ics
+        create,   // POST     /api/calendar
+        update,   // PUT      /api/calendar/<planned_fleet_id>
+        delete,   // DELETE   /api/calendar/<planned_fleet_id>
+        signup,   // POST     /api/calendar/<planned_fleet_id>/signup
+        withdraw, // DELETE   /api/calendar/<planned_fleet_id>/signup
+    ]
+}
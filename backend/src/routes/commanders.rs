@@ -2,17 +2,38 @@ use std::cmp::Ordering;
 
 use crate::{
     app::Application,
-    core::auth::{get_access_keys, AuthenticatedAccount},
+    core::{
+        audit,
+        auth::{get_access_keys, get_role, AuthenticatedAccount},
+        role_history,
+    },
     util::madness::Madness,
 };
 
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Deserialize)]
 struct RequestPayload {
     character_id: Option<i64>,
     role: String,
+    expires_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ChangePayload {
+    role: String,
+    // Left out to keep the current expiry, null to remove it
+    #[serde(default, deserialize_with = "present")]
+    expires_at: Option<Option<i64>>,
+}
+
+fn present<'de, D>(deserializer: D) -> Result<Option<Option<i64>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<i64>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize)]
@@ -27,6 +48,7 @@ struct Commander {
     role: String,
     granted_by: Character,
     granted_at: i64,
+    expires_at: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -48,12 +70,15 @@ async fn list(
 ) -> Result<Json<CommanderList>, Madness> {
     account.require_access("commanders-view")?;
 
+    let now = chrono::Utc::now().timestamp();
     let mut filters = Vec::new();
 
-    let rows =
-        sqlx::query!("SELECT role, count(role) as `member_count!: i64` FROM admin GROUP BY role")
-            .fetch_all(app.get_db())
-            .await?;
+    let rows = sqlx::query!(
+        "SELECT role, count(role) as `member_count!: i64` FROM admin WHERE expires_at IS NULL OR expires_at > ? GROUP BY role",
+        now
+    )
+    .fetch_all(app.get_db())
+    .await?;
 
     for acl in rows {
         filters.push(CommanderRank {
@@ -66,6 +91,7 @@ async fn list(
         "SELECT 
         role, 
         granted_at, 
+        expires_at,
         fc.id AS `id`, 
         fc.name AS `name`, 
         a.id AS `admin_id`, 
@@ -73,7 +99,10 @@ async fn list(
       FROM 
         admin
         JOIN `character` AS fc ON character_id = fc.id 
-        JOIN `character` AS a ON granted_by_id = a.id"
+        JOIN `character` AS a ON granted_by_id = a.id
+      WHERE
+        expires_at IS NULL OR expires_at > ?",
+        now
     )
     .fetch_all(app.get_db())
     .await?;
@@ -91,6 +120,7 @@ async fn list(
                 name: cmdr.admin_name,
             },
             granted_at: cmdr.granted_at,
+            expires_at: cmdr.expires_at,
         })
         .collect();
 
@@ -135,32 +165,62 @@ async fn assign(
         )));
     }
 
+    let now = chrono::Utc::now().timestamp();
+    if let Some(expires_at) = body.expires_at {
+        if expires_at <= now {
+            return Err(Madness::BadRequest(
+                "A role cannot expire in the past".to_string(),
+            ));
+        }
+    }
+
     let character_id = body.character_id.unwrap();
     if let Some(character) = sqlx::query!("SELECT * FROM `character` WHERE id=?", character_id)
         .fetch_optional(app.get_db())
         .await?
     {
         // Ensure the character doesn't already have a role - Character <-> Admin is a 1 to 1 relationship
-        if let Some(_) = sqlx::query!("SELECT * FROM admin WHERE character_id=?", character_id)
-            .fetch_optional(app.get_db())
-            .await?
-        {
+        if let Some(_) = get_role(app.get_db(), character_id).await? {
             return Err(Madness::BadRequest(format!(
                 "Cannot assign \"{}\" to {} as they already have a role",
                 body.role, character.name
             )));
         }
 
-        let now = chrono::Utc::now().timestamp();
+        let mut tx = app.get_db().begin().await?;
+        // A lapsed grant may not have been cleaned up yet
+        sqlx::query!("DELETE FROM admin WHERE character_id=?", character_id)
+            .execute(&mut tx)
+            .await?;
         sqlx::query!(
-            "INSERT INTO admin VALUES (?, ?, ?, ?)",
+            "INSERT INTO admin (character_id, role, granted_at, granted_by_id, expires_at) VALUES (?, ?, ?, ?, ?)",
             character_id,
             body.role,
             now,
-            account.id
+            account.id,
+            body.expires_at
+        )
+        .execute(&mut tx)
+        .await?;
+        role_history::record(
+            &mut tx,
+            character_id,
+            "grant",
+            &body.role,
+            None,
+            body.expires_at,
+            Some(account.id),
         )
-        .execute(app.get_db())
         .await?;
+        audit::log(
+            &mut tx,
+            account.id,
+            "role.assign",
+            Some(character_id),
+            &json!({ "role": body.role, "expires_at": body.expires_at }),
+        )
+        .await?;
+        tx.commit().await?;
 
         return Ok("Ok");
     }
@@ -170,7 +230,7 @@ async fn assign(
 }
 
 #[get("/api/commanders/roles")]
-async fn assignable(account: AuthenticatedAccount) -> Result<Json<Vec<&'static str>>, Madness> {
+async fn assignable(account: AuthenticatedAccount) -> Result<Json<Vec<String>>, Madness> {
     account.require_access("commanders-manage")?;
 
     let role_order = vec!["trainee", "trainee-advanced", "fc", "fc-trainer", "council"];
@@ -181,13 +241,13 @@ async fn assignable(account: AuthenticatedAccount) -> Result<Json<Vec<&'static s
             // 14 is the index of ":".
             let (_, b) = scope.split_at(18);
 
-            options.push(b);
+            options.push(b.to_string());
         }
     }
 
     options.sort_by(|a, b| {
-        if let Some(a) = role_order.iter().position(|&r| &r == a) {
-            if let Some(b) = role_order.iter().position(|&r| &r == b) {
+        if let Some(a) = role_order.iter().position(|&r| r == a.as_str()) {
+            if let Some(b) = role_order.iter().position(|&r| r == b.as_str()) {
                 if a < b {
                     return Ordering::Less;
                 } else {
@@ -210,17 +270,105 @@ async fn lookup(
 ) -> Result<String, Madness> {
     account.require_access("commanders-manage")?;
 
-    if let Some(role) = sqlx::query!("Select * FROM admin WHERE character_id=?", character_id)
-        .fetch_optional(app.get_db())
-        .await?
-    {
-        return Ok(role.role);
+    if let Some(role) = get_role(app.get_db(), character_id).await? {
+        return Ok(role);
     }
 
     // todo: return error message
     return Err(Madness::NotFound(""));
 }
 
+#[patch("/api/commanders/<character_id>", data = "<body>")]
+async fn change(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    character_id: i64,
+    body: Json<ChangePayload>,
+) -> Result<&'static str, Madness> {
+    account.require_access("commanders-manage")?;
+
+    if account.id == character_id {
+        return Err(Madness::BadRequest(format!(
+            "You cannot change your own rank."
+        )));
+    }
+
+    if get_access_keys(&body.role).is_none() {
+        return Err(Madness::BadRequest(format!(
+            "The FC rank \"{}\" does not exist",
+            body.role
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    if let Some(Some(expires_at)) = body.expires_at {
+        if expires_at <= now {
+            return Err(Madness::BadRequest(
+                "A role cannot expire in the past".to_string(),
+            ));
+        }
+    }
+
+    let current = match sqlx::query!(
+        "SELECT role, expires_at FROM admin WHERE character_id=? AND (expires_at IS NULL OR expires_at > ?)",
+        character_id,
+        now
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        Some(current) => current,
+        None => return Err(Madness::NotFound("Character does not have a role")),
+    };
+    let expires_at = body.expires_at.unwrap_or(current.expires_at);
+    // A reminder already sent about the same expiry doesn't need to go out again
+    let reset_reminder = expires_at != current.expires_at;
+    let current = current.role;
+
+    // Changing a role means taking the old one away and granting the new one
+    for role in [&current, &body.role] {
+        let required_scope = format!("commanders-manage:{}", role);
+        if !account.access.contains(&required_scope) {
+            return Err(Madness::Forbidden(format!(
+                "You do not have permission to manage the role \"{}\"",
+                role
+            )));
+        }
+    }
+
+    let mut tx = app.get_db().begin().await?;
+    sqlx::query!(
+        "UPDATE admin SET role=?, expires_at=?, reminded_at=IF(?, NULL, reminded_at) WHERE character_id=?",
+        body.role,
+        expires_at,
+        reset_reminder,
+        character_id
+    )
+    .execute(&mut tx)
+    .await?;
+    role_history::record(
+        &mut tx,
+        character_id,
+        "change",
+        &body.role,
+        Some(&current),
+        expires_at,
+        Some(account.id),
+    )
+    .await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "role.change",
+        Some(character_id),
+        &json!({ "role": body.role, "previous_role": current, "expires_at": expires_at }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("Ok")
+}
+
 #[delete("/api/commanders/<character_id>")]
 async fn revoke(
     account: AuthenticatedAccount,
@@ -251,9 +399,29 @@ async fn revoke(
         }
 
         // Revoke the role
+        let mut tx = app.get_db().begin().await?;
         sqlx::query!("DELETE FROM admin WHERE character_id=?", character_id)
-            .execute(app.get_db())
+            .execute(&mut tx)
             .await?;
+        role_history::record(
+            &mut tx,
+            character_id,
+            "revoke",
+            &role.role,
+            None,
+            None,
+            Some(account.id),
+        )
+        .await?;
+        audit::log(
+            &mut tx,
+            account.id,
+            "role.revoke",
+            Some(character_id),
+            &json!({ "role": role.role }),
+        )
+        .await?;
+        tx.commit().await?;
     };
 
     return Ok("Ok");
@@ -265,6 +433,7 @@ pub fn routes() -> Vec<rocket::Route> {
         list,       // GET      /api/commanders
         assignable, // GET      /api/commanders/roles
         lookup,     // GET      /api/commanders/<character_id>
+        change,     // PATCH    /api/commanders/<character_id>
         revoke      // DELETE   /api/commanders/<character_id>
     ]
 }
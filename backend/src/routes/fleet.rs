@@ -1,20 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     app::Application,
     core::{
+        audit,
         auth::{authorize_character, AuthenticatedAccount},
-        esi::{ESIError, ESIScope},
+        esi::{
+            fleet_wings::{self, ESIFleetWing},
+            ESIError, ESIScope,
+        },
+        fleet_layout::layout_name,
+        webhook,
     },
     util::{
-        self,
         madness::Madness,
         types::{Character, Hull},
     },
 };
 use eve_data_core::TypeDB;
+use futures::{stream, StreamExt};
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Debug, Serialize)]
 struct FleetStatusFleet {
@@ -51,46 +58,35 @@ async fn get_current_fleet_id(
     app: &rocket::State<Application>,
     character_id: i64,
 ) -> Result<i64, Madness> {
-    #[derive(Debug, Deserialize)]
-    struct BasicInfo {
-        fleet_id: i64,
-    }
-
-    let basic_info = app
-        .esi_client
-        .get(
-            &format!("/v1/characters/{}/fleet", character_id),
-            character_id,
-            ESIScope::Fleets_ReadFleet_v1,
-        )
-        .await;
+    let basic_info = crate::core::esi::character_fleet::get(&app.esi_client, character_id).await;
     if let Err(whatswrong) = basic_info {
         match whatswrong {
             ESIError::Status(404) => return Err(Madness::NotFound("You are not in a fleet")),
             e => return Err(e.into()),
         };
     }
-    let basic_info: BasicInfo = basic_info.unwrap();
-    Ok(basic_info.fleet_id)
+    Ok(basic_info.unwrap().fleet_id)
 }
 
 #[derive(Debug, Serialize)]
 struct FleetInfoResponse {
     fleet_id: i64,
-    wings: Vec<FleetInfoWing>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct FleetInfoWing {
-    id: i64,
-    name: String,
-    squads: Vec<FleetInfoSquad>,
+    wings: Vec<ESIFleetWing>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct FleetInfoSquad {
-    id: i64,
-    name: String,
+async fn get_wings(
+    app: &rocket::State<Application>,
+    fleet_id: i64,
+    character_id: i64,
+) -> Result<Vec<ESIFleetWing>, Madness> {
+    let wings = fleet_wings::get(&app.esi_client, fleet_id, character_id).await;
+    if let Err(whatswrong) = wings {
+        match whatswrong {
+            ESIError::Status(404) => return Err(Madness::NotFound("You are not the fleet boss")),
+            e => return Err(e.into()),
+        };
+    }
+    Ok(wings?)
 }
 
 #[get("/api/fleet/info?<character_id>")]
@@ -104,21 +100,7 @@ async fn fleet_info(
 
     let fleet_id = get_current_fleet_id(app, character_id).await?;
 
-    let wings = app
-        .esi_client
-        .get(
-            &format!("/v1/fleets/{}/wings", fleet_id),
-            character_id,
-            ESIScope::Fleets_ReadFleet_v1,
-        )
-        .await;
-    if let Err(whatswrong) = wings {
-        match whatswrong {
-            ESIError::Status(404) => return Err(Madness::NotFound("You are not the fleet boss")),
-            e => return Err(e.into()),
-        };
-    }
-    let wings: Vec<FleetInfoWing> = wings?;
+    let wings = get_wings(app, fleet_id, character_id).await?;
 
     Ok(Json(FleetInfoResponse { fleet_id, wings }))
 }
@@ -134,6 +116,8 @@ struct FleetMembersMember {
     name: Option<String>,
     ship: Hull,
     wl_category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flag: Option<String>,
 }
 
 #[get("/api/fleet/members?<character_id>")]
@@ -174,6 +158,16 @@ async fn fleet_members(
     .map(|squad| (squad.squad_id, squad.category))
     .collect();
 
+    let mut flags: HashMap<i64, String> = sqlx::query!(
+        "SELECT character_id, flag FROM fleet_flag WHERE fleet_id = ?",
+        fleet_id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|flag| (flag.character_id, flag.flag))
+    .collect();
+
     Ok(Json(FleetMembersResponse {
         members: in_fleet
             .into_iter()
@@ -188,11 +182,48 @@ async fn fleet_members(
                     .get(&member.squad_id)
                     .and_then(|s| category_lookup.get(s.as_str()))
                     .map(|s| s.to_string()),
+                flag: flags.remove(&member.character_id),
             })
             .collect(),
     }))
 }
 
+async fn save_layout(
+    tx: &mut crate::DBTX<'_>,
+    fleet_id: i64,
+    boss_id: i64,
+    assignments: &HashMap<String, (i64, i64)>,
+) -> Result<(), Madness> {
+    // Overflow squads created for invites stay, the fleet still has them
+    sqlx::query!(
+        "DELETE FROM fleet_squad WHERE fleet_id=? AND is_overflow=0",
+        fleet_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO fleet (id, boss_id) VALUES (?, ?) ON DUPLICATE KEY UPDATE boss_id=VALUES(boss_id)",
+        fleet_id,
+        boss_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for category in crate::data::categories::categories() {
+        if let Some((wing_id, squad_id)) = assignments.get(&category.id) {
+            sqlx::query!("INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE wing_id=VALUES(wing_id), is_overflow=0",
+            fleet_id, wing_id, squad_id, category.id).execute(&mut *tx).await?;
+        } else {
+            return Err(Madness::BadRequest(format!(
+                "Missing assignment for {}",
+                category.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 struct RegisterRequest {
     character_id: i64,
@@ -210,44 +241,234 @@ async fn register_fleet(
     authorize_character(app.get_db(), &account, input.character_id, None).await?;
 
     let mut tx = app.get_db().begin().await?;
-    sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", input.fleet_id)
-        .execute(&mut tx)
-        .await?;
-    sqlx::query!(
-        "REPLACE INTO fleet (id, boss_id) VALUES (?, ?)",
+    save_layout(
+        &mut tx,
         input.fleet_id,
-        input.character_id
+        input.character_id,
+        &input.assignments,
     )
-    .execute(&mut tx)
     .await?;
 
-    for category in crate::data::categories::categories() {
-        if let Some((wing_id, squad_id)) = input.assignments.get(&category.id) {
-            sqlx::query!("INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category) VALUES (?, ?, ?, ?)",
-            input.fleet_id, wing_id, squad_id, category.id).execute(&mut tx).await?;
-        } else {
-            return Err(Madness::BadRequest(format!(
-                "Missing assignment for {}",
-                category.name
-            )));
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet.register",
+        Some(input.character_id),
+        &json!({ "fleet_id": input.fleet_id, "assignments": input.assignments }),
+    )
+    .await?;
+
+    webhook::emit(
+        &mut tx,
+        "fleet.register",
+        account.id,
+        format!("Fleet {} registered", input.fleet_id),
+        vec![("fleet_id", input.fleet_id.to_string())],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+const SETUP_WING_NAME: &str = "Waitlist";
+
+#[derive(Debug, Deserialize)]
+struct SetupRequest {
+    character_id: i64,
+}
+
+#[post("/api/fleet/setup", data = "<input>")]
+async fn setup_fleet(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<SetupRequest>,
+) -> Result<Json<HashMap<String, (i64, i64)>>, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    let fleet_id = get_current_fleet_id(app, input.character_id).await?;
+    let wings = get_wings(app, fleet_id, input.character_id).await?;
+    let categories = crate::data::categories::categories();
+
+    // Squads that are already named after a category are reused as-is, including any overflow
+    // squads created for it earlier
+    let mut assignments = HashMap::new();
+    let mut overflow = Vec::new();
+    for wing in &wings {
+        for squad in &wing.squads {
+            for category in categories {
+                if squad
+                    .name
+                    .eq_ignore_ascii_case(&layout_name(&category.name))
+                {
+                    if assignments.contains_key(&category.id) {
+                        overflow.push((&category.id, wing.id, squad.id));
+                    } else {
+                        assignments.insert(category.id.clone(), (wing.id, squad.id));
+                    }
+                }
+            }
         }
     }
 
+    let missing: Vec<_> = categories
+        .iter()
+        .filter(|category| !assignments.contains_key(&category.id))
+        .collect();
+
+    if !missing.is_empty() {
+        let wing_id = match wings
+            .iter()
+            .find(|wing| wing.name.eq_ignore_ascii_case(SETUP_WING_NAME))
+        {
+            Some(wing) => wing.id,
+            None => {
+                fleet_wings::create_wing(
+                    &app.esi_client,
+                    fleet_id,
+                    input.character_id,
+                    SETUP_WING_NAME,
+                )
+                .await?
+            }
+        };
+
+        for category in missing {
+            let squad_id = fleet_wings::create_squad(
+                &app.esi_client,
+                fleet_id,
+                wing_id,
+                input.character_id,
+                &layout_name(&category.name),
+            )
+            .await?;
+            assignments.insert(category.id.clone(), (wing_id, squad_id));
+        }
+    }
+
+    let mut tx = app.get_db().begin().await?;
+    save_layout(&mut tx, fleet_id, input.character_id, &assignments).await?;
+    for (category, wing_id, squad_id) in overflow {
+        sqlx::query!(
+            "INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category, is_overflow) VALUES (?, ?, ?, ?, 1) ON DUPLICATE KEY UPDATE wing_id=VALUES(wing_id)",
+            fleet_id,
+            wing_id,
+            squad_id,
+            category
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet.setup",
+        Some(input.character_id),
+        &json!({ "fleet_id": fleet_id, "assignments": assignments }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(assignments))
+}
+
+#[derive(Debug, Deserialize)]
+struct AutoReassignRequest {
+    fleet_id: i64,
+    enable: bool,
+}
+
+#[post("/api/fleet/auto_reassign", data = "<input>")]
+async fn set_auto_reassign(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<AutoReassignRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+
+    let fleet = match sqlx::query!("SELECT boss_id FROM fleet WHERE id=?", input.fleet_id)
+        .fetch_optional(app.get_db())
+        .await?
+    {
+        Some(fleet) => fleet,
+        None => return Err(Madness::NotFound("Fleet not configured")),
+    };
+    authorize_character(app.get_db(), &account, fleet.boss_id, None).await?;
+
+    let mut tx = app.get_db().begin().await?;
+    sqlx::query!(
+        "UPDATE fleet SET auto_reassign=? WHERE id=?",
+        input.enable,
+        input.fleet_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet.auto_reassign",
+        Some(fleet.boss_id),
+        &json!({ "fleet_id": input.fleet_id, "enable": input.enable }),
+    )
+    .await?;
     tx.commit().await?;
 
     Ok("OK")
 }
 
+const KICK_CONCURRENCY: usize = 10;
+
 #[derive(Debug, Deserialize)]
 struct FleetCloseRequest {
     character_id: i64,
+    #[serde(default)]
+    preview: bool,
+    #[serde(default)]
+    keep_characters: Vec<i64>,
+    #[serde(default)]
+    keep_squads: Vec<i64>,
+    #[serde(default)]
+    keep_categories: Vec<String>,
+    #[serde(default)]
+    keep_fcs: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum CloseOutcome {
+    Kept,
+    WouldKick,
+    Kicked,
+    AlreadyGone,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetCloseMember {
+    id: i64,
+    name: Option<String>,
+    squad_id: i64,
+    wl_category: Option<String>,
+    outcome: CloseOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetCloseResponse {
+    message: String,
+    members: Vec<FleetCloseMember>,
 }
+
 #[post("/api/fleet/close", data = "<input>")]
 async fn close_fleet(
     app: &rocket::State<Application>,
     account: AuthenticatedAccount,
     input: Json<FleetCloseRequest>,
-) -> Result<String, Madness> {
+) -> Result<Json<FleetCloseResponse>, Madness> {
     authorize_character(app.get_db(), &account, input.character_id, None).await?;
     account.require_access("fleet-configure")?;
 
@@ -255,50 +476,185 @@ async fn close_fleet(
 
     let in_fleet =
         crate::core::esi::fleet_members::get(&app.esi_client, fleet_id, input.character_id).await?;
+    let character_ids: Vec<_> = in_fleet.iter().map(|member| member.character_id).collect();
+    let mut characters = crate::data::character::lookup(app.get_db(), &character_ids).await?;
 
-    let mut success = 0;
-    let total = in_fleet.len();
+    let squads: HashMap<i64, String> = sqlx::query!(
+        "SELECT squad_id, category FROM fleet_squad WHERE fleet_id = ?",
+        fleet_id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|squad| (squad.squad_id, squad.category))
+    .collect();
 
-    for member in in_fleet {
-        if member.character_id == input.character_id {
-            continue;
+    // FCs are kept together with the other characters on their account
+    let fcs: HashSet<i64> = match input.keep_fcs {
+        true => {
+            let now = chrono::Utc::now().timestamp();
+            sqlx::query!(
+                "
+                    SELECT character_id `character_id!: i64` FROM admin
+                    WHERE expires_at IS NULL OR expires_at > ?
+                    UNION
+                    SELECT alt_id FROM alt_character
+                    JOIN admin ON admin.character_id = alt_character.account_id
+                    WHERE admin.expires_at IS NULL OR admin.expires_at > ?
+                    UNION
+                    SELECT account_id FROM alt_character
+                    JOIN admin ON admin.character_id = alt_character.alt_id
+                    WHERE admin.expires_at IS NULL OR admin.expires_at > ?
+                ",
+                now,
+                now,
+                now
+            )
+            .fetch_all(app.get_db())
+            .await?
+            .into_iter()
+            .map(|fc| fc.character_id)
+            .collect()
         }
+        false => HashSet::new(),
+    };
 
-        let res = app
-            .esi_client
-            .delete(
-                &format!("/v1/fleets/{}/members/{}/", fleet_id, member.character_id),
-                input.character_id,
-                ESIScope::Fleets_WriteFleet_v1,
-            )
-            .await;
+    let mut members = Vec::new();
+    for member in in_fleet {
+        let wl_category = squads.get(&member.squad_id).cloned();
+        let keep = member.character_id == input.character_id
+            || fcs.contains(&member.character_id)
+            || input.keep_characters.contains(&member.character_id)
+            || input.keep_squads.contains(&member.squad_id)
+            || wl_category
+                .as_ref()
+                .map_or(false, |c| input.keep_categories.contains(c));
+
+        members.push(FleetCloseMember {
+            id: member.character_id,
+            name: characters.remove(&member.character_id).map(|c| c.name),
+            squad_id: member.squad_id,
+            wl_category,
+            outcome: match keep {
+                true => CloseOutcome::Kept,
+                false => CloseOutcome::WouldKick,
+            },
+            error: None,
+        });
+    }
 
-        if let Err(e) = res {
-            match e {
-                ESIError::Status(code) => match code {
-                    404 => continue,
-                    _ => (),
-                },
-                _ => (),
+    let to_kick = members
+        .iter()
+        .filter(|member| member.outcome == CloseOutcome::WouldKick)
+        .count();
+
+    if input.preview {
+        return Ok(Json(FleetCloseResponse {
+            message: format!(
+                "Would remove {} of {} fleet members.",
+                to_kick,
+                members.len()
+            ),
+            members,
+        }));
+    }
+
+    let esi_client = &app.esi_client;
+    let boss_id = input.character_id;
+    let members: Vec<FleetCloseMember> = stream::iter(members)
+        .map(|mut member| async move {
+            if member.outcome != CloseOutcome::WouldKick {
+                return member;
             }
 
-            return Err(util::madness::Madness::ESIError(e));
-        }
+            let res = esi_client
+                .delete(
+                    &format!("/v1/fleets/{}/members/{}/", fleet_id, member.id),
+                    boss_id,
+                    ESIScope::Fleets_WriteFleet_v1,
+                )
+                .await;
+
+            member.outcome = match res {
+                Ok(()) => CloseOutcome::Kicked,
+                Err(ESIError::Status(404)) => CloseOutcome::AlreadyGone,
+                Err(e) => {
+                    member.error = Some(e.to_string());
+                    CloseOutcome::Failed
+                }
+            };
+            member
+        })
+        .buffer_unordered(KICK_CONCURRENCY)
+        .collect()
+        .await;
 
-        if res.is_ok() {
-            success += 1;
-        }
-    }
+    let kicked = members
+        .iter()
+        .filter(|member| member.outcome == CloseOutcome::Kicked)
+        .count();
+    let failed: Vec<i64> = members
+        .iter()
+        .filter(|member| member.outcome == CloseOutcome::Failed)
+        .map(|member| member.id)
+        .collect();
+    // Anyone besides the boss still in the fleet means it wasn't emptied
+    let partial = !failed.is_empty()
+        || members
+            .iter()
+            .any(|member| member.outcome == CloseOutcome::Kept && member.id != boss_id);
 
-    if (success + 1) == total {
-        return Ok(format!("All fleet members kicked."));
-    }
+    let mut tx = app.get_db().begin().await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet.close",
+        Some(input.character_id),
+        &json!({
+            "fleet_id": fleet_id,
+            "kicked": kicked,
+            "failed": failed,
+            "keep_characters": input.keep_characters,
+            "keep_squads": input.keep_squads,
+            "keep_categories": input.keep_categories,
+            "keep_fcs": input.keep_fcs,
+            "partial": partial,
+        }),
+    )
+    .await?;
+
+    webhook::emit(
+        &mut tx,
+        "fleet.close",
+        account.id,
+        match partial {
+            true => format!(
+                "Fleet {} partly closed, {} members removed",
+                fleet_id, kicked
+            ),
+            false => format!("Fleet {} closed, {} members removed", fleet_id, kicked),
+        },
+        vec![
+            ("fleet_id", fleet_id.to_string()),
+            ("kicked", kicked.to_string()),
+            ("partial", partial.to_string()),
+        ],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    let message = match failed.is_empty() {
+        true => format!("Removed {} of {} fleet members.", kicked, to_kick),
+        false => format!(
+            "Removed {} of {} fleet members, {} could not be removed.",
+            kicked,
+            to_kick,
+            failed.len()
+        ),
+    };
 
-    Ok(format!(
-        "Removed {} of {} fleet members.",
-        success,
-        (total - 1)
-    ))
+    Ok(Json(FleetCloseResponse { message, members }))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -307,6 +663,8 @@ pub fn routes() -> Vec<rocket::Route> {
         fleet_info,
         close_fleet,
         fleet_members,
-        register_fleet
+        register_fleet,
+        setup_fleet,
+        set_auto_reassign
     ]
 }
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    app::Application,
+    core::{
+        audit,
+        auth::{authorize_character, AuthenticatedAccount},
+        esi::ESIScope,
+    },
+    util::{madness::Madness, template, types::Character},
+};
+
+#[derive(Debug, Serialize)]
+struct MotdTemplate {
+    id: i64,
+    name: String,
+    body: String,
+    updated_by: Character,
+    updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MotdTemplateRequest {
+    name: String,
+    body: String,
+}
+
+impl MotdTemplateRequest {
+    fn validate(&self) -> Result<(), Madness> {
+        if self.name.is_empty() || self.name.len() > 64 {
+            return Err(Madness::BadRequest(
+                "Template name must be between 1 and 64 characters".to_string(),
+            ));
+        }
+        if self.body.is_empty() {
+            return Err(Madness::BadRequest(
+                "Template body cannot be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[get("/api/fleet/motd")]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<MotdTemplate>>, Madness> {
+    account.require_access("fleet-configure")?;
+
+    let templates = sqlx::query!(
+        "
+            SELECT fleet_motd.id, fleet_motd.name, body, updated_by_id, `character`.name updated_by_name, updated_at
+            FROM fleet_motd
+            JOIN `character` ON `character`.id = updated_by_id
+            ORDER BY fleet_motd.name
+        "
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|row| MotdTemplate {
+        id: row.id,
+        name: row.name,
+        body: row.body,
+        updated_by: Character {
+            id: row.updated_by_id,
+            name: row.updated_by_name,
+            corporation_id: None,
+        },
+        updated_at: row.updated_at,
+    })
+    .collect();
+
+    Ok(Json(templates))
+}
+
+#[post("/api/fleet/motd", data = "<input>")]
+async fn create(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<MotdTemplateRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    input.validate()?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "INSERT INTO fleet_motd (name, body, updated_by_id, updated_at) VALUES (?, ?, ?, ?)",
+        input.name,
+        input.body,
+        account.id,
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+    let template_id = crate::last_insert_id!(result);
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet_motd.create",
+        None,
+        &json!({ "template_id": template_id, "name": input.name, "body": input.body }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[put("/api/fleet/motd/<template_id>", data = "<input>")]
+async fn update(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    template_id: i64,
+    input: Json<MotdTemplateRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    input.validate()?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "UPDATE fleet_motd SET name=?, body=?, updated_by_id=?, updated_at=? WHERE id=?",
+        input.name,
+        input.body,
+        account.id,
+        now,
+        template_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Madness::NotFound("Template not found"));
+    }
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet_motd.update",
+        None,
+        &json!({ "template_id": template_id, "name": input.name, "body": input.body }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/fleet/motd/<template_id>")]
+async fn delete(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    template_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!("DELETE FROM fleet_motd WHERE id=?", template_id)
+        .execute(&mut tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Madness::NotFound("Template not found"));
+    }
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet_motd.delete",
+        None,
+        &json!({ "template_id": template_id }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[derive(Debug, Deserialize)]
+struct PushRequest {
+    fleet_id: i64,
+    template_id: Option<i64>,
+    comms: Option<String>,
+    // Falls back to the calendar fleet running on the fleet's waitlist
+    doctrine: Option<String>,
+    is_free_move: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    motd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_free_move: Option<bool>,
+}
+
+// The current doctrine is the one of the calendar fleet running right now on the waitlist the fleet
+// invites from, preferring a fleet the boss signed up to FC. Until the fleet has invited anyone its
+// waitlist isn't known, and any running calendar fleet counts.
+async fn current_doctrine(
+    db: &crate::DB,
+    fleet_id: i64,
+    boss_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+
+    let waitlist_id = sqlx::query!(
+        "SELECT waitlist_id FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL",
+        fleet_id
+    )
+    .fetch_optional(db)
+    .await?
+    .and_then(|session| session.waitlist_id);
+
+    Ok(sqlx::query!(
+        "
+            SELECT doctrine FROM planned_fleet
+            LEFT JOIN planned_fleet_signup ON planned_fleet_signup.planned_fleet_id = planned_fleet.id
+                AND planned_fleet_signup.character_id = ? AND planned_fleet_signup.role = 'fc'
+            WHERE starts_at <= ? AND ends_at > ? AND (? IS NULL OR waitlist_id = ?)
+            ORDER BY planned_fleet_signup.character_id IS NULL, starts_at DESC
+            LIMIT 1
+        ",
+        boss_id,
+        now,
+        now,
+        waitlist_id,
+        waitlist_id
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|fleet| fleet.doctrine))
+}
+
+#[post("/api/fleet/motd/push", data = "<input>")]
+async fn push(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<PushRequest>,
+) -> Result<Json<FleetSettings>, Madness> {
+    account.require_access("fleet-configure")?;
+
+    if input.template_id.is_none() && input.is_free_move.is_none() {
+        return Err(Madness::BadRequest(
+            "Nothing to change: specify a template and/or free-move".to_string(),
+        ));
+    }
+
+    let fleet = match sqlx::query!(
+        "SELECT boss_id, name boss_name FROM fleet JOIN `character` ON `character`.id = boss_id WHERE fleet.id = ?",
+        input.fleet_id
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        Some(fleet) => fleet,
+        None => return Err(Madness::NotFound("Fleet not configured")),
+    };
+    authorize_character(app.get_db(), &account, fleet.boss_id, None).await?;
+
+    let motd = match input.template_id {
+        Some(template_id) => {
+            let template = match sqlx::query!("SELECT body FROM fleet_motd WHERE id=?", template_id)
+                .fetch_optional(app.get_db())
+                .await?
+            {
+                Some(template) => template,
+                None => return Err(Madness::NotFound("Template not found")),
+            };
+
+            let mut values = HashMap::new();
+            values.insert("fc", fleet.boss_name);
+            values.insert("comms", input.comms.clone().unwrap_or_default());
+            let doctrine = match &input.doctrine {
+                Some(doctrine) => Some(doctrine.clone()),
+                None => current_doctrine(app.get_db(), input.fleet_id, fleet.boss_id).await?,
+            };
+            match doctrine {
+                Some(doctrine) => {
+                    values.insert("doctrine", doctrine);
+                }
+                None if template.body.contains("{doctrine}") => {
+                    return Err(Madness::BadRequest(
+                        "No doctrine given and no planned fleet is running right now".to_string(),
+                    ))
+                }
+                None => {}
+            }
+            Some(template::render(&template.body, &values))
+        }
+        None => None,
+    };
+
+    let settings = FleetSettings {
+        motd,
+        is_free_move: input.is_free_move,
+    };
+
+    app.esi_client
+        .put(
+            &format!("/v1/fleets/{}/", input.fleet_id),
+            &settings,
+            fleet.boss_id,
+            ESIScope::Fleets_WriteFleet_v1,
+        )
+        .await?;
+
+    audit::log(
+        app.get_db(),
+        account.id,
+        "fleet.settings",
+        Some(fleet.boss_id),
+        &json!({
+            "fleet_id": input.fleet_id,
+            "template_id": input.template_id,
+            "is_free_move": input.is_free_move,
+        }),
+    )
+    .await?;
+
+    Ok(Json(settings))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,   // GET      /api/fleet/motd
+        create, // POST     /api/fleet/motd
+        update, // PUT      /api/fleet/motd/<template_id>
+        delete, // DELETE   /api/fleet/motd/<template_id>
+        push,   // POST     /api/fleet/motd/push
+    ]
+}
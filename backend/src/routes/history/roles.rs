@@ -0,0 +1,75 @@
+use crate::{
+    app,
+    core::auth::{authorize_character, AuthenticatedAccount},
+    util::{madness::Madness, types::Character},
+};
+
+use rocket::serde::json::Json;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+struct RoleHistoryLine {
+    action: String,
+    role: String,
+    previous_role: Option<String>,
+    expires_at: Option<i64>,
+    changed_by: Option<Character>,
+    changed_at: i64,
+}
+
+#[derive(Serialize, Debug)]
+struct RoleHistory {
+    history: Vec<RoleHistoryLine>,
+}
+
+#[get("/api/history/roles?<character_id>")]
+async fn role_history(
+    character_id: i64,
+    account: AuthenticatedAccount,
+    app: &rocket::State<app::Application>,
+) -> Result<Json<RoleHistory>, Madness> {
+    authorize_character(
+        app.get_db(),
+        &account,
+        character_id,
+        Some("commanders-view"),
+    )
+    .await?;
+
+    let history = sqlx::query!(
+        "
+        SELECT action, role, previous_role, expires_at, changed_at,
+            changed_by_id, changed_by.name `changed_by_name?`
+        FROM admin_history
+        LEFT JOIN `character` changed_by ON admin_history.changed_by_id=changed_by.id
+        WHERE character_id = ?
+        ORDER BY admin_history.id DESC
+    ",
+        character_id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|line| RoleHistoryLine {
+        action: line.action,
+        role: line.role,
+        previous_role: line.previous_role,
+        expires_at: line.expires_at,
+        changed_by: match (line.changed_by_id, line.changed_by_name) {
+            (Some(id), Some(name)) => Some(Character {
+                id,
+                name,
+                corporation_id: None,
+            }),
+            _ => None,
+        },
+        changed_at: line.changed_at,
+    })
+    .collect();
+
+    Ok(Json(RoleHistory { history }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![role_history]
+}
@@ -0,0 +1,221 @@
+use crate::{
+    app::Application,
+    core::auth::AuthenticatedAccount,
+    util::{
+        madness::Madness,
+        types::{Character, Hull},
+    },
+};
+
+use eve_data_core::{TypeDB, TypeID};
+use rocket::serde::json::Json;
+use serde::Serialize;
+
+const PAGE_SIZE: i64 = 50;
+
+struct SessionRow {
+    id: i64,
+    fleet_id: i64,
+    waitlist_id: Option<i64>,
+    waitlist_name: Option<String>,
+    started_at: i64,
+    ended_at: Option<i64>,
+    last_update: i64,
+    peak_size: i32,
+    member_seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionSummary {
+    id: i64,
+    fleet_id: i64,
+    waitlist_id: Option<i64>,
+    waitlist_name: Option<String>,
+    started_at: i64,
+    ended_at: Option<i64>,
+    duration: i64,
+    peak_size: i32,
+    average_size: f64,
+    pilot_hours: f64,
+}
+
+impl From<SessionRow> for SessionSummary {
+    fn from(row: SessionRow) -> Self {
+        let duration = row.ended_at.unwrap_or(row.last_update) - row.started_at;
+        SessionSummary {
+            id: row.id,
+            fleet_id: row.fleet_id,
+            waitlist_id: row.waitlist_id,
+            waitlist_name: row.waitlist_name,
+            started_at: row.started_at,
+            ended_at: row.ended_at,
+            duration,
+            peak_size: row.peak_size,
+            average_size: match duration {
+                0 => 0.0,
+                _ => row.member_seconds as f64 / duration as f64,
+            },
+            pilot_hours: row.member_seconds as f64 / 3600.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SessionListResponse {
+    sessions: Vec<SessionSummary>,
+    next: Option<i64>,
+}
+
+#[get("/api/history/fleet-sessions?<before>")]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    before: Option<i64>,
+) -> Result<Json<SessionListResponse>, Madness> {
+    account.require_access("fleet-history-view")?;
+
+    let rows = sqlx::query_as!(
+        SessionRow,
+        "
+            SELECT
+                fleet_session.id, fleet_id, waitlist_id, waitlist.name `waitlist_name?`,
+                started_at, ended_at, last_update, peak_size, member_seconds
+            FROM fleet_session
+            LEFT JOIN waitlist ON waitlist.id = fleet_session.waitlist_id
+            WHERE (? IS NULL OR fleet_session.id < ?)
+            ORDER BY fleet_session.id DESC
+            LIMIT ?
+        ",
+        before,
+        before,
+        PAGE_SIZE
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let next = match rows.len() as i64 == PAGE_SIZE {
+        true => rows.last().map(|r| r.id),
+        false => None,
+    };
+
+    Ok(Json(SessionListResponse {
+        sessions: rows.into_iter().map(SessionSummary::from).collect(),
+        next,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct SessionBoss {
+    character: Character,
+    started_at: i64,
+    ended_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionMember {
+    character: Character,
+    hull: Hull,
+    first_seen: i64,
+    last_seen: i64,
+    is_boss: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionDetailResponse {
+    session: SessionSummary,
+    bosses: Vec<SessionBoss>,
+    members: Vec<SessionMember>,
+}
+
+#[get("/api/history/fleet-sessions/<session_id>")]
+async fn detail(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    session_id: i64,
+) -> Result<Json<SessionDetailResponse>, Madness> {
+    account.require_access("fleet-history-view")?;
+
+    let session = match sqlx::query_as!(
+        SessionRow,
+        "
+            SELECT
+                fleet_session.id, fleet_id, waitlist_id, waitlist.name `waitlist_name?`,
+                started_at, ended_at, last_update, peak_size, member_seconds
+            FROM fleet_session
+            LEFT JOIN waitlist ON waitlist.id = fleet_session.waitlist_id
+            WHERE fleet_session.id = ?
+        ",
+        session_id
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        Some(session) => session,
+        None => return Err(Madness::NotFound("Fleet session not found")),
+    };
+
+    let bosses = sqlx::query!(
+        "
+            SELECT character_id, name, started_at, ended_at
+            FROM fleet_session_boss
+            JOIN `character` ON `character`.id = character_id
+            WHERE session_id = ?
+            ORDER BY fleet_session_boss.id
+        ",
+        session_id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|boss| SessionBoss {
+        character: Character {
+            id: boss.character_id,
+            name: boss.name,
+            corporation_id: None,
+        },
+        started_at: boss.started_at,
+        ended_at: boss.ended_at,
+    })
+    .collect();
+
+    let activity = sqlx::query!(
+        "
+            SELECT character_id, name, hull, first_seen, last_seen, is_boss
+            FROM fleet_activity
+            JOIN `character` ON `character`.id = character_id
+            WHERE fleet_id = ?
+            ORDER BY first_seen
+        ",
+        session.fleet_id
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let mut members = Vec::new();
+    for entry in activity {
+        members.push(SessionMember {
+            character: Character {
+                id: entry.character_id,
+                name: entry.name,
+                corporation_id: None,
+            },
+            hull: Hull {
+                id: entry.hull as TypeID,
+                name: TypeDB::name_of(entry.hull as TypeID)?,
+            },
+            first_seen: entry.first_seen,
+            last_seen: entry.last_seen,
+            is_boss: entry.is_boss > 0,
+        });
+    }
+
+    Ok(Json(SessionDetailResponse {
+        session: session.into(),
+        bosses,
+        members,
+    }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![list, detail]
+}
@@ -0,0 +1,134 @@
+use rocket::http::CookieJar;
+use rocket::serde::json::Json;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    app::Application,
+    core::{
+        audit,
+        auth::{get_access_keys, session_account_id, AuthenticatedAccount},
+    },
+    util::madness::Madness,
+};
+
+const DEFAULT_DURATION: i64 = 30 * 60;
+const MAX_DURATION: i64 = 2 * 60 * 60;
+
+#[derive(Debug, Deserialize)]
+struct ImpersonateRequest {
+    role: Option<String>,
+    character_id: Option<i64>,
+    duration: Option<i64>,
+}
+
+#[post("/api/auth/impersonate", data = "<input>")]
+async fn start(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<ImpersonateRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("impersonate")?;
+
+    match (&input.role, input.character_id) {
+        (Some(role), None) => {
+            if get_access_keys(role).is_none() {
+                return Err(Madness::BadRequest(format!(
+                    "The role \"{}\" does not exist",
+                    role
+                )));
+            }
+        }
+        (None, Some(character_id)) => {
+            if sqlx::query!("SELECT id FROM `character` WHERE id=?", character_id)
+                .fetch_optional(app.get_db())
+                .await?
+                .is_none()
+            {
+                return Err(Madness::NotFound("Character not found"));
+            }
+        }
+        _ => {
+            return Err(Madness::BadRequest(
+                "Specify either a role or a character to impersonate".to_string(),
+            ))
+        }
+    };
+
+    let duration = input.duration.unwrap_or(DEFAULT_DURATION);
+    if duration <= 0 || duration > MAX_DURATION {
+        return Err(Madness::BadRequest(format!(
+            "Duration must be between 1 and {} seconds",
+            MAX_DURATION
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = now + duration;
+
+    let mut tx = app.get_db().begin().await?;
+    sqlx::query!(
+        "UPDATE impersonation SET ended_at=? WHERE account_id=? AND ended_at IS NULL",
+        now,
+        account.id
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO impersonation (account_id, role, target_id, started_at, expires_at) VALUES (?, ?, ?, ?, ?)",
+        account.id,
+        input.role,
+        input.character_id,
+        now,
+        expires_at
+    )
+    .execute(&mut tx)
+    .await?;
+    audit::log(
+        &mut tx,
+        account.id,
+        "impersonation.start",
+        input.character_id,
+        &json!({ "role": input.role, "expires_at": expires_at }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+// This endpoint deliberately doesn't use AuthenticatedAccount, as that would be read-only (and
+// resolve to the impersonated account) while the impersonation is running.
+#[delete("/api/auth/impersonate")]
+async fn stop(
+    app: &rocket::State<Application>,
+    cookies: &CookieJar<'_>,
+) -> Result<&'static str, Madness> {
+    let account_id = match session_account_id(cookies, &app.token_secret) {
+        Some(id) => id,
+        None => return Err(Madness::AccessDenied),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "UPDATE impersonation SET ended_at=? WHERE account_id=? AND ended_at IS NULL",
+        now,
+        account_id
+    )
+    .execute(&mut tx)
+    .await?;
+    if result.rows_affected() > 0 {
+        audit::log(&mut tx, account_id, "impersonation.stop", None, &json!({})).await?;
+    }
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        start, // POST     /api/auth/impersonate
+        stop,  // DELETE   /api/auth/impersonate
+    ]
+}
@@ -1,48 +1,64 @@
 mod announcements;
+mod api_tokens;
+mod audit;
 mod auth;
 mod badges;
 mod bans;
+mod calendar;
 mod categories;
 mod commanders;
 mod fittings;
 mod fleet;
+mod fleet_motd;
 mod healthcheck;
 mod history;
+mod impersonation;
 mod implants;
 mod modules;
 mod notes;
+mod notifications;
 mod pilot;
 mod search;
 mod skillplans;
 mod skills;
 mod sse;
 mod statistics;
+mod status;
 mod waitlist;
+mod webhooks;
 mod window;
 
 pub fn routes() -> Vec<rocket::Route> {
     [
         announcements::routes(),
+        api_tokens::routes(),
+        audit::routes(),
         auth::routes(),
         sse::routes(),
         skills::routes(),
         pilot::routes(),
         history::routes(),
+        impersonation::routes(),
         window::routes(),
         badges::routes(),
         bans::routes(),
+        calendar::routes(),
         commanders::routes(),
         modules::routes(),
         search::routes(),
         categories::routes(),
         fleet::routes(),
+        fleet_motd::routes(),
         waitlist::routes(),
         statistics::routes(),
+        status::routes(),
         healthcheck::routes(),
         implants::routes(),
         notes::routes(),
+        notifications::routes(),
         skillplans::routes(),
         fittings::routes(),
+        webhooks::routes(),
     ]
     .concat()
 }
@@ -0,0 +1,165 @@
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Application,
+    core::{
+        auth::AuthenticatedAccount,
+        notification::{self, Kind},
+    },
+    util::madness::Madness,
+};
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+struct NotificationEntry {
+    id: i64,
+    kind: String,
+    message: String,
+    created_at: i64,
+    read_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct InboxResponse {
+    notifications: Vec<NotificationEntry>,
+    unread: i64,
+    next: Option<i64>,
+}
+
+#[get("/api/notifications?<before>&<unread>")]
+async fn inbox(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    before: Option<i64>,
+    unread: Option<bool>,
+) -> Result<Json<InboxResponse>, Madness> {
+    let unread_only = unread.unwrap_or(false);
+
+    let notifications = sqlx::query_as!(
+        NotificationEntry,
+        "
+            SELECT id, kind, message, created_at, read_at FROM notification
+            WHERE account_id=? AND (? IS NULL OR id < ?) AND (? = 0 OR read_at IS NULL)
+            ORDER BY id DESC
+            LIMIT ?
+        ",
+        account.id,
+        before,
+        before,
+        unread_only,
+        PAGE_SIZE
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let unread = sqlx::query!(
+        "SELECT COUNT(*) `count!: i64` FROM notification WHERE account_id=? AND read_at IS NULL",
+        account.id
+    )
+    .fetch_one(app.get_db())
+    .await?
+    .count;
+
+    let next = match notifications.len() as i64 == PAGE_SIZE {
+        true => notifications.last().map(|n| n.id),
+        false => None,
+    };
+
+    Ok(Json(InboxResponse {
+        notifications,
+        unread,
+        next,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct MarkReadRequest {
+    // Everything is marked as read if no id is given
+    id: Option<i64>,
+}
+
+#[post("/api/notifications/read", data = "<input>")]
+async fn mark_read(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<MarkReadRequest>,
+) -> Result<&'static str, Madness> {
+    let now = chrono::Utc::now().timestamp();
+
+    sqlx::query!(
+        "UPDATE notification SET read_at=? WHERE account_id=? AND (? IS NULL OR id=?) AND read_at IS NULL",
+        now,
+        account.id,
+        input.id,
+        input.id
+    )
+    .execute(app.get_db())
+    .await?;
+
+    Ok("OK")
+}
+
+#[derive(Debug, Serialize)]
+struct PreferenceEntry {
+    kind: &'static str,
+    #[serde(flatten)]
+    preference: notification::Preference,
+}
+
+#[get("/api/notifications/preferences")]
+async fn preferences(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<PreferenceEntry>>, Madness> {
+    let mut entries = Vec::new();
+    for &kind in Kind::ALL.iter() {
+        entries.push(PreferenceEntry {
+            kind: kind.as_str(),
+            preference: notification::preference(app.get_db(), account.id, kind).await?,
+        });
+    }
+
+    Ok(Json(entries))
+}
+
+#[derive(Debug, Deserialize)]
+struct SetPreferenceRequest {
+    kind: String,
+    inbox: bool,
+    sse: bool,
+}
+
+#[post("/api/notifications/preferences", data = "<input>")]
+async fn set_preference(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<SetPreferenceRequest>,
+) -> Result<&'static str, Madness> {
+    let kind = match Kind::parse(&input.kind) {
+        Some(kind) => kind,
+        None => return Err(Madness::BadRequest("Unknown notification kind".to_string())),
+    };
+
+    sqlx::query!(
+        "REPLACE INTO notification_preference (account_id, kind, inbox, sse) VALUES (?, ?, ?, ?)",
+        account.id,
+        kind.as_str(),
+        input.inbox,
+        input.sse
+    )
+    .execute(app.get_db())
+    .await?;
+
+    Ok("OK")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        inbox,          // GET      /api/notifications
+        mark_read,      // POST     /api/notifications/read
+        preferences,    // GET      /api/notifications/preferences
+        set_preference, // POST     /api/notifications/preferences
+    ]
+}
@@ -2,7 +2,7 @@ use rocket::serde::json::Json;
 
 use crate::{
     app,
-    core::auth::{authorize_character, get_access_keys, AuthenticatedAccount},
+    core::auth::{authorize_character, get_access_keys, get_role, AuthenticatedAccount},
     util::{
         madness::Madness,
         types::{Character, CharacterAndLevel},
@@ -24,11 +24,8 @@ async fn pilot_info(
     let mut tags: Vec<String> = Vec::new();
 
     // Add the ACL tag to the array
-    if let Some(admin) = sqlx::query!("SELECT role FROM admin WHERE character_id=?", character.id)
-        .fetch_optional(app.get_db())
-        .await?
-    {
-        let keys = get_access_keys(&admin.role).unwrap();
+    if let Some(role) = get_role(app.get_db(), character.id).await? {
+        let keys = get_access_keys(&role).unwrap();
         if keys.contains("waitlist-tag:HQ-FC") {
             tags.push("HQ-FC".to_string());
         } else if keys.contains("waitlist-tag:TRAINEE") {
@@ -1,12 +1,36 @@
-use rocket::response::Redirect;
+use futures::stream::{BoxStream, StreamExt};
+use rocket::response::stream::{Event, EventStream};
+use rocket::response::{self, Redirect, Responder};
+use rocket::{Request, Shutdown};
+use tokio::sync::broadcast::error::RecvError;
 
-use crate::core::auth::AuthenticatedAccount;
+use crate::core::{auth::AuthenticatedAccount, sse::Subscription, waitlist_state::Visibility};
+
+enum StreamResponse {
+    Redirect(Redirect),
+    Stream(EventStream<BoxStream<'static, Event>>),
+}
+
+// An event stream only responds for the lifetime of the request, which the derive doesn't allow
+impl<'r> Responder<'r, 'r> for StreamResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'r> {
+        match self {
+            StreamResponse::Redirect(redirect) => redirect.respond_to(request),
+            StreamResponse::Stream(stream) => stream.respond_to(request),
+        }
+    }
+}
 
 #[get("/api/sse/stream")]
-fn stream(app: &rocket::State<crate::app::Application>, account: AuthenticatedAccount) -> Redirect {
+fn stream(
+    app: &rocket::State<crate::app::Application>,
+    account: AuthenticatedAccount,
+    shutdown: Shutdown,
+) -> StreamResponse {
     let mut topics = vec![
         "announcments".to_string(),
         "waitlist".to_string(),
+        Visibility::of(&account).topic().to_string(),
         format!("account;{}", account.id),
     ];
 
@@ -14,7 +38,27 @@ fn stream(app: &rocket::State<crate::app::Application>, account: AuthenticatedAc
         topics.push("fleet_comp".to_string());
     }
 
-    Redirect::temporary(app.sse_client.events_url(&topics))
+    let receiver = match app.sse_client.subscribe(&topics) {
+        Subscription::Redirect(url) => return StreamResponse::Redirect(Redirect::temporary(url)),
+        Subscription::Stream(receiver) => receiver,
+    };
+
+    let events = futures::stream::unfold((receiver, topics), |(mut receiver, topics)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if topics.contains(&event.topic) => {
+                    let event = Event::data(event.data).event(event.event);
+                    return Some((event, (receiver, topics)));
+                }
+                Ok(_) => continue,
+                // A slow client misses some events, it'll catch up on the next one
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    StreamResponse::Stream(EventStream::from(events.take_until(shutdown).boxed()))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -3,7 +3,11 @@ use std::collections::BTreeMap;
 use eve_data_core::{TypeDB, TypeError, TypeID};
 use rocket::serde::json::Json;
 
-use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+use crate::{
+    app::Application,
+    core::{auth::AuthenticatedAccount, role_history::RoleTimeline},
+    util::madness::Madness,
+};
 
 use serde::Serialize;
 
@@ -210,6 +214,50 @@ impl Queries {
         Ok(result)
     }
 
+    async fn boss_seconds_by_role_by_month(
+        db: &crate::DB,
+    ) -> Result<BTreeMap<YearMonth, BTreeMap<String, f64>>, sqlx::Error> {
+        #[derive(sqlx::FromRow)]
+        struct Result {
+            yearmonth: String,
+            character_id: i64,
+            first_seen: i64,
+            time_in_fleet: i64,
+        }
+
+        let res: Vec<Result> = sqlx::query_as(concat!(
+            "
+            SELECT
+                ",
+            year_month!(from_unixtime!("first_seen")),
+            " yearmonth,
+                character_id,
+                first_seen,
+                CAST(last_seen - first_seen AS SIGNED) time_in_fleet
+            FROM fleet_activity
+            WHERE is_boss = 1
+        "
+        ))
+        .fetch_all(db)
+        .await?;
+
+        // Credit the fleet to the role the boss held when it happened, not the one they hold now
+        let timeline = RoleTimeline::load(db).await?;
+        let mut result = BTreeMap::new();
+        for row in res {
+            let role = timeline
+                .role_at(row.character_id, row.first_seen)
+                .unwrap_or("No role");
+            *result
+                .entry(YearMonth::parse(&row.yearmonth))
+                .or_insert_with(BTreeMap::new)
+                .entry(role.to_string())
+                .or_insert(0.) += row.time_in_fleet as f64;
+        }
+
+        Ok(result)
+    }
+
     async fn xes_by_hull_by_month(
         db: &crate::DB,
     ) -> Result<BTreeMap<YearMonth, BTreeMap<TypeID, f64>>, sqlx::Error> {
@@ -412,6 +460,7 @@ struct StatsResponse {
     fleet_seconds_by_hull_28d: BTreeMap<String, f64>,
     x_vs_time_by_hull_28d: BTreeMap<String, BTreeMap<&'static str, f64>>,
     time_spent_in_fleet_by_month: BTreeMap<YearMonth, BTreeMap<&'static str, f64>>,
+    boss_seconds_by_role_by_month: BTreeMap<YearMonth, BTreeMap<String, f64>>,
 }
 
 #[get("/api/stats")]
@@ -427,6 +476,7 @@ async fn statistics(
     let xes_by_hull_month = Queries::xes_by_hull_by_month(app.get_db()).await?;
     let xes_by_hull_28d = Queries::xes_by_hull_28d(app.get_db()).await?;
     let seconds_by_hull_28d = Queries::fleet_seconds_by_hull_28d(app.get_db()).await?;
+    let boss_seconds_by_role_month = Queries::boss_seconds_by_role_by_month(app.get_db()).await?;
 
     Ok(Json(StatsResponse {
         fleet_seconds_by_hull_by_month: Displayer::build_fleet_seconds_by_hull_by_month(
@@ -446,6 +496,7 @@ async fn statistics(
         time_spent_in_fleet_by_month: Displayer::build_time_spent_in_fleet_by_month(
             &seconds_by_character_month,
         ),
+        boss_seconds_by_role_by_month: boss_seconds_by_role_month,
     }))
 }
 
@@ -0,0 +1,69 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+
+use crate::{app::Application, core::public_status::Status, util::madness::Madness};
+
+#[derive(Responder)]
+struct StatusResponse {
+    inner: Json<Status>,
+    // Websites embed this, so let any origin read it; there's nothing private in here
+    cors: Header<'static>,
+}
+
+// The address requests are rate limited on: the configured proxy header if there is one, the
+// connecting address otherwise. Rocket's own client_ip() trusts X-Real-IP from anyone.
+struct ClientAddress(Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientAddress {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let app = req.guard::<&rocket::State<Application>>().await.unwrap();
+
+        let address = match &app.config.public_status.trusted_proxy_header {
+            // Proxies append to lists like X-Forwarded-For, so the last entry is the one they saw
+            Some(header) => req
+                .headers()
+                .get(header)
+                .last()
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|value| value.trim().parse().ok()),
+            None => req.remote().map(|remote| remote.ip()),
+        };
+
+        Outcome::Success(ClientAddress(address))
+    }
+}
+
+#[get("/api/public/status")]
+async fn status(
+    app: &rocket::State<Application>,
+    client: ClientAddress,
+) -> Result<StatusResponse, Madness> {
+    if !app.public_status.enabled() {
+        return Err(Madness::NotFound("Public status is not enabled"));
+    }
+
+    // Requests without a known address share one bucket
+    if !app
+        .public_status
+        .allow(client.0.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)))
+    {
+        return Err(Madness::TooManyRequests);
+    }
+
+    Ok(StatusResponse {
+        inner: Json(app.public_status.get().await?),
+        cors: Header::new("Access-Control-Allow-Origin", "*"),
+    })
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        status, // GET      /api/public/status
+    ]
+}
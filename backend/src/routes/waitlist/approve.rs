@@ -1,7 +1,12 @@
 use rocket::serde::json::Json;
 use serde::Deserialize;
+use serde_json::json;
 
-use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+use crate::{
+    app::Application,
+    core::{audit, auth::AuthenticatedAccount, waitlist_state::Change},
+    util::madness::Madness,
+};
 
 #[derive(Debug, Deserialize)]
 struct ApproveRequest {
@@ -18,7 +23,7 @@ async fn approve_fit(
 
     let entry = sqlx::query!(
         "
-            SELECT entry_id, waitlist_id FROM waitlist_entry_fit wef
+            SELECT entry_id, waitlist_id, character_id, fit_id FROM waitlist_entry_fit wef
             JOIN waitlist_entry we ON we.id=wef.entry_id WHERE wef.id=?
         ",
         input.id
@@ -26,14 +31,37 @@ async fn approve_fit(
     .fetch_one(app.get_db())
     .await?;
 
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
         "UPDATE waitlist_entry_fit SET approved=1 WHERE id=?",
         input.id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE fit_history SET approved=1 WHERE character_id=? AND fit_id=? ORDER BY id DESC LIMIT 1",
+        entry.character_id,
+        entry.fit_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "waitlist.approve",
+        Some(entry.character_id),
+        &json!({ "fit_id": input.id, "waitlist_id": entry.waitlist_id }),
+    )
     .await?;
 
-    super::notify::notify_waitlist_update(app, entry.waitlist_id).await?;
+    super::notify::notify_waitlist_changes(
+        &mut tx,
+        entry.waitlist_id,
+        &[Change::FitApproved { fit_id: input.id }],
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok("OK")
 }
@@ -54,7 +82,7 @@ async fn reject_fit(
 
     let entry = sqlx::query!(
         "
-            SELECT entry_id, waitlist_id FROM waitlist_entry_fit wef
+            SELECT entry_id, waitlist_id, character_id, fit_id FROM waitlist_entry_fit wef
             JOIN waitlist_entry we ON we.id=wef.entry_id WHERE wef.id=?
         ",
         input.id
@@ -62,15 +90,42 @@ async fn reject_fit(
     .fetch_one(app.get_db())
     .await?;
 
+    let mut tx = app.get_db().begin().await?;
     sqlx::query!(
         "UPDATE waitlist_entry_fit SET approved=0, review_comment=? WHERE id=?",
         input.review_comment,
         input.id
     )
-    .execute(app.get_db())
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE fit_history SET approved=0 WHERE character_id=? AND fit_id=? ORDER BY id DESC LIMIT 1",
+        entry.character_id,
+        entry.fit_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "waitlist.reject",
+        Some(entry.character_id),
+        &json!({
+            "fit_id": input.id,
+            "waitlist_id": entry.waitlist_id,
+            "review_comment": input.review_comment,
+        }),
+    )
     .await?;
 
-    super::notify::notify_waitlist_update(app, entry.waitlist_id).await?;
+    super::notify::notify_waitlist_changes(
+        &mut tx,
+        entry.waitlist_id,
+        &[Change::FitRejected { fit_id: input.id }],
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok("OK")
 }
@@ -1,4 +1,8 @@
-use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+use crate::{
+    app::Application,
+    core::{auth::AuthenticatedAccount, waitlist_control},
+    util::madness::Madness,
+};
 
 use rocket::serde::json::Json;
 use serde::Deserialize;
@@ -26,24 +30,7 @@ async fn empty_waitlist(
     }
 
     let mut tx = app.get_db().begin().await?;
-
-    sqlx::query!(
-        "
-            DELETE FROM waitlist_entry_fit
-            WHERE entry_id IN (SELECT id FROM waitlist_entry WHERE waitlist_id=?)
-        ",
-        input.waitlist_id
-    )
-    .execute(&mut tx)
-    .await?;
-
-    sqlx::query!(
-        "DELETE FROM waitlist_entry WHERE waitlist_id=?",
-        input.waitlist_id
-    )
-    .execute(&mut tx)
-    .await?;
-
+    waitlist_control::empty(&mut tx, account.id, input.waitlist_id, None).await?;
     tx.commit().await?;
 
     Ok("OK")
@@ -3,14 +3,19 @@ use rocket::serde::json::Json;
 use crate::{
     app::Application,
     core::{
+        audit,
         auth::{authorize_character, AuthenticatedAccount},
-        esi::ESIScope,
-        sse::Event,
+        esi::{fleet_members, ESIScope},
+        fleet_layout,
+        notification::{self, Kind},
+        waitlist_queue,
+        waitlist_state::{self, Change},
     },
     util::madness::Madness,
 };
 use eve_data_core::{TypeDB, TypeID};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Debug, Deserialize)]
 struct InviteRequest {
@@ -26,6 +31,7 @@ async fn invite(
 ) -> Result<&'static str, Madness> {
     account.require_access("fleet-invite")?;
     authorize_character(app.get_db(), &account, input.character_id, None).await?;
+    let now = chrono::Utc::now().timestamp();
     let xup = sqlx::query!(
         "
             SELECT
@@ -33,14 +39,21 @@ async fn invite(
                 wef.category wef_category,
                 wef.character_id wef_character_id,
 				wef.is_alt wef_is_alt,
+                wef.invited_at wef_invited_at,
                 we.account_id we_account_id,
+                we.waitlist_id we_waitlist_id,
+                we.joined_at we_joined_at,
                 fitting.hull fitting_hull,
-                EXISTS (SELECT character_id FROM admin WHERE character_id=we.account_id) as `has_acl!: bool`
+                EXISTS (
+                    SELECT character_id FROM admin
+                    WHERE character_id=we.account_id AND (expires_at IS NULL OR expires_at > ?)
+                ) as `has_acl!: bool`
             FROM waitlist_entry_fit wef
             JOIN waitlist_entry we ON wef.entry_id=we.id
             JOIN fitting ON wef.fit_id = fitting.id
             WHERE wef.id = ?
         ",
+        now,
         input.id
     )
     .fetch_one(app.get_db())
@@ -49,19 +62,11 @@ async fn invite(
     let select_cat = if xup.wef_is_alt > 0 {
         "alt".to_string()
     } else {
-        xup.wef_category
+        xup.wef_category.clone()
     };
-    let squad_info = match sqlx::query!(
-        "
-            SELECT fleet_id, squad_id, wing_id FROM fleet
-            JOIN fleet_squad ON fleet.id=fleet_squad.fleet_id
-            WHERE boss_id=? AND category=?
-        ",
-        input.character_id,
-        select_cat,
-    )
-    .fetch_optional(app.get_db())
-    .await?
+    let fleet = match sqlx::query!("SELECT id FROM fleet WHERE boss_id=?", input.character_id)
+        .fetch_optional(app.get_db())
+        .await?
     {
         Some(fleet) => fleet,
         None => return Err(Madness::BadRequest("Fleet not configured".to_string())),
@@ -84,6 +89,17 @@ async fn invite(
         }
     }
 
+    let members = fleet_members::get(&app.esi_client, fleet.id, input.character_id).await?;
+    let (wing_id, squad_id) = fleet_layout::squad_for_category(
+        app.get_db(),
+        &app.esi_client,
+        fleet.id,
+        input.character_id,
+        &select_cat,
+        &members,
+    )
+    .await?;
+
     #[derive(Debug, Serialize)]
     struct Invite {
         character_id: i64,
@@ -93,12 +109,12 @@ async fn invite(
     }
     app.esi_client
         .post(
-            &format!("/v1/fleets/{}/members/", squad_info.fleet_id),
+            &format!("/v1/fleets/{}/members/", fleet.id),
             &Invite {
                 character_id: xup.wef_character_id,
                 role: "squad_member",
-                squad_id: squad_info.squad_id,
-                wing_id: squad_info.wing_id,
+                squad_id,
+                wing_id,
             },
             input.character_id,
             ESIScope::Fleets_WriteFleet_v1,
@@ -109,17 +125,78 @@ async fn invite(
         .fetch_one(app.get_db())
         .await?;
 
-    app.sse_client
-        .submit(vec![Event::new(
-            &format!("account;{}", xup.we_account_id),
-            "wakeup",
-            format!(
-                "{} has invited your {} to fleet.",
-                fc.name,
-                TypeDB::name_of(xup.fitting_hull as TypeID)?
-            ),
-        )])
+    let mut tx = app.get_db().begin().await?;
+
+    // Lets the fleet updater pick up the new member quickly
+    sqlx::query!(
+        "UPDATE fleet SET last_invite_at=? WHERE id=?",
+        now,
+        fleet.id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    // The first invite decides which waitlist the fleet session is running from
+    sqlx::query!(
+        "UPDATE fleet_session SET waitlist_id=? WHERE fleet_id=? AND ended_at IS NULL AND waitlist_id IS NULL",
+        xup.we_waitlist_id,
+        fleet.id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE waitlist_entry_fit SET invited_at=? WHERE id=?",
+        now,
+        xup.wef_id
+    )
+    .execute(&mut tx)
+    .await?;
+    if xup.wef_invited_at.is_none() {
+        waitlist_queue::record_invite(
+            &mut tx,
+            xup.we_waitlist_id,
+            xup.wef_character_id,
+            fleet.id,
+            &xup.wef_category,
+            xup.we_joined_at,
+            now,
+        )
         .await?;
+    }
+    waitlist_state::publish(
+        &mut tx,
+        xup.we_waitlist_id,
+        &[Change::FitInvited { fit_id: xup.wef_id }],
+    )
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "fleet.invite",
+        Some(xup.wef_character_id),
+        &json!({
+            "fit_id": xup.wef_id,
+            "fleet_id": fleet.id,
+            "squad_id": squad_id,
+            "hull": xup.fitting_hull,
+        }),
+    )
+    .await?;
+
+    notification::send(
+        &mut tx,
+        xup.we_account_id,
+        Kind::Invite,
+        &format!(
+            "{} has invited your {} to fleet.",
+            fc.name,
+            TypeDB::name_of(xup.fitting_hull as TypeID)?
+        ),
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok("OK")
 }
@@ -1,11 +1,15 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use rocket::serde::json::{Json, Value};
+use rocket::serde::json::Json;
 use serde::Serialize;
 
 use crate::{
     app::Application,
-    core::auth::AuthenticatedAccount,
+    core::{
+        auth::AuthenticatedAccount,
+        waitlist_queue::{self, QueuePlace},
+        waitlist_state::{self, Visibility, WaitlistEntryFit},
+    },
     data,
     util::{
         madness::Madness,
@@ -17,8 +21,12 @@ use eve_data_core::{TypeDB, TypeID};
 #[derive(Debug, Serialize)]
 struct WaitlistResponse {
     open: bool,
+    version: i64,
     waitlist: Option<Vec<WaitlistEntry>>,
     categories: Vec<&'static str>,
+    // Lets clients keep the ETAs of their own x-ups current as the line moves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait_per_place: Option<HashMap<String, i64>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,22 +38,6 @@ struct WaitlistEntry {
     can_remove: bool,
 }
 
-#[derive(Debug, Serialize)]
-struct WaitlistEntryFit {
-    id: i64,
-    approved: bool,
-    category: String,
-    hull: Hull,
-    character: Option<Character>,
-    tags: Vec<String>,
-    hours_in_fleet: Option<i64>,
-    review_comment: Option<String>,
-    dna: Option<String>,
-    implants: Option<Vec<TypeID>>,
-    fit_analysis: Option<Value>,
-    is_alt: bool,
-}
-
 #[get("/api/waitlist?<waitlist_id>")]
 async fn list(
     app: &rocket::State<Application>,
@@ -56,56 +48,36 @@ async fn list(
         .iter()
         .map(|cat| &(cat.name) as &str)
         .collect();
-    let waitlist_categories_lookup: BTreeMap<_, _> = data::categories::categories()
-        .iter()
-        .map(|cat| (&cat.id, &cat.name))
-        .collect();
 
-    let waitlist = sqlx::query!("SELECT is_open FROM waitlist WHERE id = ?", waitlist_id)
-        .fetch_optional(app.get_db())
-        .await?;
-    if waitlist.is_none() || waitlist.unwrap().is_open == 0 {
-        return Ok(Json(WaitlistResponse {
-            open: false,
-            waitlist: None,
-            categories: waitlist_categories,
-        }));
-    }
-
-    let records = sqlx::query!(
-        "
-            SELECT
-                we.id we_id,
-                we.joined_at we_joined_at,
-                we.account_id we_account_id,
-                wef.id wef_id,
-                wef.approved wef_approved,
-                wef.category wef_category,
-                wef.cached_time_in_fleet wef_cached_time_in_fleet,
-                wef.review_comment wef_review_comment,
-                wef.tags wef_tags,
-                wef.fit_analysis wef_fit_analysis,
-				wef.is_alt wef_is_alt,
-                char_wef.id char_wef_id,
-                char_wef.name char_wef_name,
-                char_we.id char_we_id,
-                char_we.name char_we_name,
-                fitting.dna fitting_dna,
-                fitting.hull fitting_hull,
-                implant_set.implants implant_set_implants
-                FROM waitlist_entry_fit wef
-            JOIN waitlist_entry we ON wef.entry_id = we.id
-            JOIN `character` char_wef ON wef.character_id = char_wef.id
-            JOIN `character` char_we ON we.account_id = char_we.id
-            JOIN fitting ON wef.fit_id = fitting.id
-            JOIN implant_set ON wef.implant_set_id = implant_set.id
-            WHERE we.waitlist_id = ?
-            ORDER BY we.id ASC, wef.id ASC
-        ",
+    let waitlist = sqlx::query!(
+        "SELECT is_open, version FROM waitlist WHERE id = ?",
         waitlist_id
     )
-    .fetch_all(app.get_db())
+    .fetch_optional(app.get_db())
     .await?;
+    let version = match waitlist {
+        Some(waitlist) if waitlist.is_open > 0 => waitlist.version,
+        Some(waitlist) => {
+            return Ok(Json(WaitlistResponse {
+                open: false,
+                version: waitlist.version,
+                waitlist: None,
+                categories: waitlist_categories,
+                wait_per_place: None,
+            }))
+        }
+        None => {
+            return Ok(Json(WaitlistResponse {
+                open: false,
+                version: 0,
+                waitlist: None,
+                categories: waitlist_categories,
+                wait_per_place: None,
+            }))
+        }
+    };
+
+    let records = waitlist_state::load_fits(app.get_db(), waitlist_id, None).await?;
 
     let hulls: Vec<_> = records
         .iter()
@@ -115,99 +87,52 @@ async fn list(
         .collect();
     let hull_names = TypeDB::names_of(&hulls)?;
 
+    // Everyone gets told where their own x-ups are in line, without seeing who is ahead of them
+    let positions = waitlist_queue::positions(&records);
+    let wait_per_place = app.waitlist_queue.wait_per_place().await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let account_visibility = Visibility::of(&account);
     let mut entries = BTreeMap::new();
     for record in records {
         let x_is_ours = record.we_account_id == account.id;
+        let visibility = match x_is_ours {
+            true => Visibility::Full,
+            false => account_visibility,
+        };
 
         let entry = entries
             .entry(record.we_id)
             .or_insert_with(|| WaitlistEntry {
                 id: record.we_id,
                 fits: Vec::new(),
-                character: if x_is_ours || account.access.contains("waitlist-view") {
-                    Some(Character {
-                        id: record.char_we_id,
-                        name: record.char_we_name.clone(),
-                        corporation_id: None,
-                    })
-                } else {
-                    None
-                },
+                character: waitlist_state::entry_character(&record, visibility),
                 joined_at: record.we_joined_at,
                 can_remove: x_is_ours || account.access.contains("waitlist-manage"),
             });
 
-        let tags = vec![];
-        let mut this_fit = WaitlistEntryFit {
-            id: record.wef_id,
-            approved: record.wef_approved > 0,
-            category: waitlist_categories_lookup
-                .get(&record.wef_category)
-                .unwrap()
-                .to_string(),
-            tags,
-            hull: Hull {
-                id: record.fitting_hull as TypeID,
-                name: hull_names
-                    .get(&record.fitting_hull)
-                    .expect("Expected hull to exist")
-                    .clone(),
-            },
-            character: None,
-            hours_in_fleet: None,
-            review_comment: None,
-            dna: None,
-            implants: None,
-            fit_analysis: None,
-            is_alt: record.wef_is_alt > 0,
+        let hull = Hull {
+            id: record.fitting_hull as TypeID,
+            name: hull_names
+                .get(&record.fitting_hull)
+                .expect("Expected hull to exist")
+                .clone(),
         };
-
-        let tags = record
-            .wef_tags
-            .split(',')
-            .into_iter()
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string());
-
-        if x_is_ours || account.access.contains("waitlist-view") {
-            this_fit.character = Some(Character {
-                id: record.char_wef_id,
-                name: record.char_wef_name,
-                corporation_id: None,
-            });
-            this_fit.hours_in_fleet = Some(record.wef_cached_time_in_fleet / 3600);
-            this_fit.review_comment = record.wef_review_comment;
-            this_fit.tags = tags.collect();
-        } else {
-            this_fit.tags = tags
-                .filter(|t| data::tags::public_tags().contains(t))
-                .collect();
+        let mut fit = waitlist_state::fit_view(&record, visibility, hull);
+        if x_is_ours {
+            let position = positions.get(&record.wef_id).copied();
+            let wait = wait_per_place.get(&record.wef_category).copied();
+            fit.set_queue(QueuePlace::new(position, wait, now));
         }
-
-        if x_is_ours
-            || (account.access.contains("waitlist-view") && account.access.contains("fit-view"))
-        {
-            this_fit.dna = Some(record.fitting_dna);
-            this_fit.implants = Some(
-                record
-                    .implant_set_implants
-                    .split(':')
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<TypeID>().unwrap())
-                    .collect(),
-            );
-            if let Some(fit_analysis) = record.wef_fit_analysis {
-                this_fit.fit_analysis = rocket::serde::json::from_str(&fit_analysis).unwrap();
-            }
-        }
-
-        entry.fits.push(this_fit);
+        entry.fits.push(fit);
     }
 
     Ok(Json(WaitlistResponse {
         open: true,
+        version,
         categories: waitlist_categories,
         waitlist: Some(entries.into_iter().map(|(_id, entry)| entry).collect()),
+        wait_per_place: Some(wait_per_place.as_ref().clone()),
     }))
 }
 
@@ -5,6 +5,7 @@ mod list;
 mod notify;
 mod open;
 mod remove;
+mod schedule;
 mod xup;
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -14,6 +15,7 @@ pub fn routes() -> Vec<rocket::Route> {
         open::routes(),
         empty::routes(),
         remove::routes(),
+        schedule::routes(),
         invite::routes(),
         xup::routes(),
     ]
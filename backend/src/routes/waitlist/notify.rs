@@ -1,60 +1,30 @@
 use crate::{
-    app::Application,
-    core::sse::{Event, SSEError},
+    core::{
+        notification::{self, Kind},
+        waitlist_state::{self, Change},
+    },
+    util::madness::Madness,
 };
-use serde::Serialize;
 
-#[derive(Debug, Serialize)]
-struct WaitlistUpdate {
+pub async fn notify_waitlist_changes(
+    tx: &mut crate::DBTX<'_>,
     waitlist_id: i64,
-}
-
-#[derive(Debug, Serialize)]
-struct Message {
-    message: &'static str,
-}
-
-pub async fn notify_waitlist_update(app: &Application, waitlist_id: i64) -> Result<(), SSEError> {
-    app.sse_client
-        .submit(vec![Event::new_json(
-            "waitlist",
-            "waitlist_update",
-            &WaitlistUpdate { waitlist_id },
-        )])
-        .await?;
-    Ok(())
+    changes: &[Change],
+) -> Result<(), Madness> {
+    waitlist_state::publish(tx, waitlist_id, changes).await
 }
 
 pub async fn notify_waitlist_update_and_xup(
-    app: &Application,
+    tx: &mut crate::DBTX<'_>,
     waitlist_id: i64,
-) -> Result<(), SSEError> {
-    if let Ok(fleets) = sqlx::query!("SELECT `boss_id` FROM `fleet`")
-        .fetch_all(app.get_db())
-        .await {
-            for fleet in fleets {
-                app.sse_client
-                .submit(vec![
-                    Event::new_json(
-                        &format!("account;{}", fleet.boss_id),
-                        "message",
-                        &Message {
-                            message: "New x-up in waitlist"
-                        }
-                    )
-                ])
-                .await?;
-            }
-        }
-
-    app.sse_client
-        .submit(vec![
-            Event::new_json(
-                "waitlist",
-                "waitlist_update",
-                &WaitlistUpdate { waitlist_id },
-            )
-        ])
+    changes: &[Change],
+) -> Result<(), Madness> {
+    let fleets = sqlx::query!("SELECT `boss_id` FROM `fleet`")
+        .fetch_all(&mut *tx)
         .await?;
-    Ok(())
+    for fleet in fleets {
+        notification::send(tx, fleet.boss_id, Kind::Xup, "New x-up in waitlist").await?;
+    }
+
+    waitlist_state::publish(tx, waitlist_id, changes).await
 }
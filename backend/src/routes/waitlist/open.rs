@@ -1,7 +1,11 @@
 use rocket::serde::json::Json;
 use serde::Deserialize;
 
-use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+use crate::{
+    app::Application,
+    core::{auth::AuthenticatedAccount, waitlist_control},
+    util::madness::Madness,
+};
 
 #[derive(Debug, Deserialize)]
 struct SetOpenRequest {
@@ -17,15 +21,9 @@ async fn set_open(
 ) -> Result<&'static str, Madness> {
     account.require_access("waitlist-edit")?;
 
-    sqlx::query!(
-        "UPDATE waitlist SET is_open=? WHERE id=?",
-        input.open,
-        input.waitlist_id
-    )
-    .execute(app.get_db())
-    .await?;
-
-    super::notify::notify_waitlist_update(app, input.waitlist_id).await?;
+    let mut tx = app.get_db().begin().await?;
+    waitlist_control::set_open(&mut tx, account.id, input.waitlist_id, input.open, None).await?;
+    tx.commit().await?;
 
     Ok("OK")
 }
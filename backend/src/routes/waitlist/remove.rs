@@ -1,9 +1,14 @@
 use rocket::serde::json::Json;
 use serde::Deserialize;
+use serde_json::json;
 
 use crate::{
     app::Application,
-    core::auth::{authorize_character, AuthenticatedAccount},
+    core::{
+        audit,
+        auth::{authorize_character, AuthenticatedAccount},
+        waitlist_state::Change,
+    },
     util::madness::Madness,
 };
 
@@ -20,7 +25,7 @@ async fn remove_fit(
 ) -> Result<&'static str, Madness> {
     let waitlist_entry = sqlx::query!(
         "
-            SELECT account_id, entry_id, waitlist_id FROM waitlist_entry_fit wef
+            SELECT account_id, entry_id, waitlist_id, character_id FROM waitlist_entry_fit wef
             JOIN waitlist_entry we ON wef.entry_id=we.id
             WHERE wef.id=?
         ",
@@ -49,6 +54,10 @@ async fn remove_fit(
     .fetch_optional(&mut tx)
     .await?;
 
+    let mut changes = vec![Change::FitRemoved {
+        entry_id: waitlist_entry.entry_id,
+        fit_id: input.id,
+    }];
     if remaining.is_none() {
         sqlx::query!(
             "DELETE FROM waitlist_entry WHERE id=?",
@@ -56,11 +65,24 @@ async fn remove_fit(
         )
         .execute(&mut tx)
         .await?;
+        changes.push(Change::EntryRemoved {
+            entry_id: waitlist_entry.entry_id,
+        });
     }
 
-    tx.commit().await?;
+    if waitlist_entry.account_id != account.id {
+        audit::log(
+            &mut tx,
+            account.id,
+            "waitlist.remove_fit",
+            Some(waitlist_entry.character_id),
+            &json!({ "fit_id": input.id, "waitlist_id": waitlist_entry.waitlist_id }),
+        )
+        .await?;
+    }
 
-    super::notify::notify_waitlist_update(app, waitlist_entry.waitlist_id).await?;
+    super::notify::notify_waitlist_changes(&mut tx, waitlist_entry.waitlist_id, &changes).await?;
+    tx.commit().await?;
 
     Ok("OK")
 }
@@ -98,10 +120,24 @@ async fn remove_x(
     sqlx::query!("DELETE FROM waitlist_entry WHERE id=?", input.id)
         .execute(&mut tx)
         .await?;
+    if entry.account_id != account.id {
+        audit::log(
+            &mut tx,
+            account.id,
+            "waitlist.remove_x",
+            Some(entry.account_id),
+            &json!({ "entry_id": input.id, "waitlist_id": entry.waitlist_id }),
+        )
+        .await?;
+    }
+    super::notify::notify_waitlist_changes(
+        &mut tx,
+        entry.waitlist_id,
+        &[Change::EntryRemoved { entry_id: input.id }],
+    )
+    .await?;
     tx.commit().await?;
 
-    super::notify::notify_waitlist_update(app, entry.waitlist_id).await?;
-
     Ok("OK")
 }
 
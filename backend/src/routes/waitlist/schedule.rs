@@ -0,0 +1,194 @@
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    app::Application,
+    core::{audit, auth::AuthenticatedAccount},
+    util::{madness::Madness, types::Character},
+};
+
+#[derive(Debug, Serialize)]
+struct ScheduleEntry {
+    id: i64,
+    waitlist_id: i64,
+    opens_at: i64,
+    closes_at: i64,
+    repeat_every: Option<i64>,
+    empty_on_close: bool,
+    is_opened: bool,
+    created_by: Character,
+    created_at: i64,
+}
+
+#[get("/api/waitlist/schedules?<waitlist_id>")]
+async fn list(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    waitlist_id: i64,
+) -> Result<Json<Vec<ScheduleEntry>>, Madness> {
+    account.require_access("waitlist-edit")?;
+
+    let schedules = sqlx::query!(
+        "
+            SELECT
+                waitlist_schedule.id, waitlist_id, opens_at, closes_at, repeat_every, empty_on_close,
+                is_opened, created_by_id, `character`.name AS created_by_name, created_at
+            FROM waitlist_schedule
+            JOIN `character` ON `character`.id = created_by_id
+            WHERE waitlist_id=?
+            ORDER BY opens_at
+        ",
+        waitlist_id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|schedule| ScheduleEntry {
+        id: schedule.id,
+        waitlist_id: schedule.waitlist_id,
+        opens_at: schedule.opens_at,
+        closes_at: schedule.closes_at,
+        repeat_every: schedule.repeat_every,
+        empty_on_close: schedule.empty_on_close > 0,
+        is_opened: schedule.is_opened > 0,
+        created_by: Character {
+            id: schedule.created_by_id,
+            name: schedule.created_by_name,
+            corporation_id: None,
+        },
+        created_at: schedule.created_at,
+    })
+    .collect();
+
+    Ok(Json(schedules))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateScheduleRequest {
+    waitlist_id: i64,
+    opens_at: i64,
+    closes_at: i64,
+    // Seconds between windows, e.g. 604800 for the same time every week. One-off if missing.
+    repeat_every: Option<i64>,
+    empty_on_close: bool,
+}
+
+#[post("/api/waitlist/schedules", data = "<input>")]
+async fn create(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<CreateScheduleRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("waitlist-edit")?;
+
+    let now = chrono::Utc::now().timestamp();
+    if input.closes_at <= input.opens_at {
+        return Err(Madness::BadRequest(
+            "The waitlist has to open before it closes".to_string(),
+        ));
+    }
+    if input.closes_at <= now {
+        return Err(Madness::BadRequest(
+            "The schedule has to end in the future".to_string(),
+        ));
+    }
+    if let Some(every) = input.repeat_every {
+        if every < 3600 || every < input.closes_at - input.opens_at {
+            return Err(Madness::BadRequest(
+                "Repeating windows must not overlap and be at least an hour apart".to_string(),
+            ));
+        }
+    }
+
+    if sqlx::query!("SELECT id FROM waitlist WHERE id=?", input.waitlist_id)
+        .fetch_optional(app.get_db())
+        .await?
+        .is_none()
+    {
+        return Err(Madness::NotFound("Waitlist not found"));
+    }
+
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "
+            INSERT INTO waitlist_schedule
+                (waitlist_id, opens_at, closes_at, repeat_every, empty_on_close, created_by_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        ",
+        input.waitlist_id,
+        input.opens_at,
+        input.closes_at,
+        input.repeat_every,
+        input.empty_on_close,
+        account.id,
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "waitlist.schedule.create",
+        None,
+        &json!({
+            "schedule_id": crate::last_insert_id!(result),
+            "waitlist_id": input.waitlist_id,
+            "opens_at": input.opens_at,
+            "closes_at": input.closes_at,
+            "repeat_every": input.repeat_every,
+            "empty_on_close": input.empty_on_close,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/waitlist/schedules/<schedule_id>")]
+async fn delete(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    schedule_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("waitlist-edit")?;
+
+    let mut tx = app.get_db().begin().await?;
+    let schedule = match sqlx::query!(
+        "SELECT waitlist_id FROM waitlist_schedule WHERE id=?",
+        schedule_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    {
+        Some(schedule) => schedule,
+        None => return Err(Madness::NotFound("Schedule not found")),
+    };
+
+    // Removing a schedule leaves the waitlist as it is, even if the schedule opened it
+    sqlx::query!("DELETE FROM waitlist_schedule WHERE id=?", schedule_id)
+        .execute(&mut tx)
+        .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "waitlist.schedule.delete",
+        None,
+        &json!({ "schedule_id": schedule_id, "waitlist_id": schedule.waitlist_id }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,   // GET      /api/waitlist/schedules
+        create, // POST     /api/waitlist/schedules
+        delete, // DELETE   /api/waitlist/schedules/<schedule_id>
+    ]
+}
@@ -5,7 +5,10 @@ use serde::Deserialize;
 
 use crate::{
     app::Application,
-    core::auth::{authorize_character, AuthenticatedAccount},
+    core::{
+        auth::{authorize_character, AuthenticatedAccount},
+        waitlist_state::Change,
+    },
     data::{implants, skills},
     tdf,
     util::madness::Madness,
@@ -156,7 +159,7 @@ async fn xup_multi(
                 implants,
                 time_in_fleet: *time_in_fleet,
                 skills,
-                access_keys: account.access,
+                access_keys: &account.access,
             },
         );
     }
@@ -202,6 +205,7 @@ async fn xup_multi(
     }
 
     // Actually write the individual entries now
+    let mut changes = Vec::new();
     for (character_id, fit) in xups {
         fit.validate()?;
         let this_pilot_data = pilot_data.get(&character_id).unwrap();
@@ -211,9 +215,16 @@ async fn xup_multi(
 
         // Delete existing X'up for the hull
         if let Some(existing_x) = sqlx::query!("
-        SELECT waitlist_entry_fit.id FROM waitlist_entry_fit JOIN fitting ON fit_id=fitting.id WHERE character_id = ? AND hull = ?
+        SELECT waitlist_entry_fit.id, entry_id, waitlist_id FROM waitlist_entry_fit JOIN fitting ON fit_id=fitting.id
+        JOIN waitlist_entry ON entry_id=waitlist_entry.id WHERE character_id = ? AND hull = ?
         ",character_id, fit.hull).fetch_optional(&mut tx).await? {
             sqlx::query!("DELETE FROM waitlist_entry_fit WHERE id = ?", existing_x.id).execute(&mut tx).await?;
+            let removed = Change::FitRemoved { entry_id: existing_x.entry_id, fit_id: existing_x.id };
+            if existing_x.waitlist_id == waitlist_id {
+                changes.push(removed);
+            } else {
+                super::notify::notify_waitlist_changes(&mut tx, existing_x.waitlist_id, &[removed]).await?;
+            }
         }
 
         let badges: Vec<String> = sqlx::query!(
@@ -238,25 +249,28 @@ async fn xup_multi(
             .map(|f| serde_json::to_string(&f).unwrap());
 
         // Add the fit to the waitlist
-        sqlx::query!("
+        let result = sqlx::query!("
             INSERT INTO waitlist_entry_fit (character_id, entry_id, fit_id, category, approved, tags, implant_set_id, fit_analysis, cached_time_in_fleet, is_alt)
             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ", character_id, entry_id, fit_id, fit_checked.category, fit_checked.approved, tags, implant_set_id, fit_analysis, this_pilot_data.time_in_fleet, is_alt)
         .execute(&mut tx).await?;
+        changes.push(Change::FitAdded {
+            fit_id: crate::last_insert_id!(result),
+        });
 
         // Log the x'up
         sqlx::query!(
-            "INSERT INTO fit_history (character_id, fit_id, implant_set_id, logged_at) VALUES (?, ?, ?, ?)",
-            character_id, fit_id, implant_set_id, now,
+            "INSERT INTO fit_history (character_id, fit_id, implant_set_id, logged_at, approved) VALUES (?, ?, ?, ?, ?)",
+            character_id, fit_id, implant_set_id, now, fit_checked.approved,
         ).execute(&mut tx).await?;
     }
 
+    // Let people and listeners know what just happened
+    super::notify::notify_waitlist_update_and_xup(&mut tx, waitlist_id, &changes).await?;
+
     // Done! Commit
     tx.commit().await?;
 
-    // Let people and listeners know what just happened
-    super::notify::notify_waitlist_update_and_xup(app, waitlist_id).await?;
-
     Ok(())
 }
 
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    app::Application,
+    core::{audit, auth::AuthenticatedAccount, webhook},
+    util::madness::Madness,
+};
+
+const DELIVERY_PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+struct WebhookEntry {
+    id: i64,
+    name: String,
+    url: String,
+    has_secret: bool,
+    events: Vec<String>,
+    template: Option<String>,
+    enabled: bool,
+    created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ListResponse {
+    webhooks: Vec<WebhookEntry>,
+    events: &'static [&'static str],
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookRequest {
+    name: String,
+    url: String,
+    // Leaving the secret out of an update keeps the current one, an empty string removes it
+    secret: Option<String>,
+    events: Vec<String>,
+    template: Option<String>,
+    enabled: bool,
+}
+
+fn validate(input: &WebhookRequest) -> Result<(), Madness> {
+    if input.name.is_empty() || input.name.len() > 64 {
+        return Err(Madness::BadRequest(
+            "Webhook name must be between 1 and 64 characters".to_string(),
+        ));
+    }
+    if !(input.url.starts_with("https://") || input.url.starts_with("http://"))
+        || input.url.len() > 512
+    {
+        return Err(Madness::BadRequest("Invalid webhook URL".to_string()));
+    }
+    if input.events.is_empty() {
+        return Err(Madness::BadRequest(
+            "A webhook needs at least one event".to_string(),
+        ));
+    }
+    for event in &input.events {
+        if !webhook::EVENTS.contains(&event.as_str()) {
+            return Err(Madness::BadRequest(format!("Unknown event \"{}\"", event)));
+        }
+    }
+    if let Some(template) = &input.template {
+        webhook::validate_template(template).map_err(Madness::BadRequest)?;
+    }
+
+    Ok(())
+}
+
+#[get("/api/webhooks")]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<ListResponse>, Madness> {
+    account.require_access("webhooks-manage")?;
+
+    let webhooks = sqlx::query!(
+        "SELECT id, name, url, secret, events, template, enabled, created_at FROM webhook ORDER BY id"
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|webhook| WebhookEntry {
+        id: webhook.id,
+        name: webhook.name,
+        url: webhook.url,
+        has_secret: webhook.secret.is_some(),
+        events: webhook.events.split(',').map(|e| e.to_string()).collect(),
+        template: webhook.template,
+        enabled: webhook.enabled > 0,
+        created_at: webhook.created_at,
+    })
+    .collect();
+
+    Ok(Json(ListResponse {
+        webhooks,
+        events: webhook::EVENTS,
+    }))
+}
+
+#[post("/api/webhooks", data = "<input>")]
+async fn create(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<WebhookRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("webhooks-manage")?;
+    validate(&input)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let secret = input.secret.as_ref().filter(|s| !s.is_empty());
+    let events = input.events.join(",");
+
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "INSERT INTO webhook (name, url, secret, events, template, enabled, created_by_id, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        input.name,
+        input.url,
+        secret,
+        events,
+        input.template,
+        input.enabled,
+        account.id,
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "webhook.create",
+        None,
+        &json!({
+            "webhook_id": crate::last_insert_id!(result),
+            "name": input.name,
+            "events": input.events,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[put("/api/webhooks/<webhook_id>", data = "<input>")]
+async fn update(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    webhook_id: i64,
+    input: Json<WebhookRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("webhooks-manage")?;
+    validate(&input)?;
+
+    let events = input.events.join(",");
+
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!(
+        "UPDATE webhook SET name=?, url=?, events=?, template=?, enabled=? WHERE id=?",
+        input.name,
+        input.url,
+        events,
+        input.template,
+        input.enabled,
+        webhook_id
+    )
+    .execute(&mut tx)
+    .await?;
+    if result.rows_affected() == 0
+        && sqlx::query!("SELECT id FROM webhook WHERE id=?", webhook_id)
+            .fetch_optional(&mut tx)
+            .await?
+            .is_none()
+    {
+        return Err(Madness::NotFound("Webhook not found"));
+    }
+
+    if let Some(secret) = &input.secret {
+        let secret = Some(secret).filter(|s| !s.is_empty());
+        sqlx::query!("UPDATE webhook SET secret=? WHERE id=?", secret, webhook_id)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "webhook.update",
+        None,
+        &json!({
+            "webhook_id": webhook_id,
+            "name": input.name,
+            "events": input.events,
+            "enabled": input.enabled,
+            "secret_changed": input.secret.is_some(),
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/webhooks/<webhook_id>")]
+async fn delete(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    webhook_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("webhooks-manage")?;
+
+    let mut tx = app.get_db().begin().await?;
+    let result = sqlx::query!("DELETE FROM webhook WHERE id=?", webhook_id)
+        .execute(&mut tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(Madness::NotFound("Webhook not found"));
+    }
+
+    audit::log(
+        &mut tx,
+        account.id,
+        "webhook.delete",
+        None,
+        &json!({ "webhook_id": webhook_id }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok("OK")
+}
+
+// Queues a sample delivery so the receiving end can be checked without waiting for a real event
+#[post("/api/webhooks/<webhook_id>/test")]
+async fn send_test(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    webhook_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("webhooks-manage")?;
+
+    let webhook = match sqlx::query!("SELECT template FROM webhook WHERE id=?", webhook_id)
+        .fetch_optional(app.get_db())
+        .await?
+    {
+        Some(webhook) => webhook,
+        None => return Err(Madness::NotFound("Webhook not found")),
+    };
+
+    let mut vars = HashMap::new();
+    vars.insert("event", "test".to_string());
+    vars.insert("message", "Test message from the waitlist".to_string());
+    vars.insert("actor", account.id.to_string());
+    vars.insert("timestamp", chrono::Utc::now().timestamp().to_string());
+    let payload = webhook::render_payload(webhook.template.as_deref(), &vars);
+
+    webhook::queue(app.get_db(), webhook_id, "test", &payload).await?;
+
+    Ok("OK")
+}
+
+#[derive(Debug, Serialize)]
+struct DeliveryEntry {
+    id: i64,
+    event: String,
+    status: String,
+    attempts: i32,
+    next_attempt_at: i64,
+    last_error: Option<String>,
+    created_at: i64,
+    delivered_at: Option<i64>,
+}
+
+#[get("/api/webhooks/<webhook_id>/deliveries?<before>")]
+async fn deliveries(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    webhook_id: i64,
+    before: Option<i64>,
+) -> Result<Json<Vec<DeliveryEntry>>, Madness> {
+    account.require_access("webhooks-manage")?;
+
+    let deliveries = sqlx::query_as!(
+        DeliveryEntry,
+        "
+            SELECT id, event, status, attempts, next_attempt_at, last_error, created_at, delivered_at
+            FROM webhook_delivery
+            WHERE webhook_id=? AND (? IS NULL OR id < ?)
+            ORDER BY id DESC
+            LIMIT ?
+        ",
+        webhook_id,
+        before,
+        before,
+        DELIVERY_PAGE_SIZE
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    Ok(Json(deliveries))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,       // GET      /api/webhooks
+        create,     // POST     /api/webhooks
+        update,     // PUT      /api/webhooks/<webhook_id>
+        delete,     // DELETE   /api/webhooks/<webhook_id>
+        send_test,  // POST     /api/webhooks/<webhook_id>/test
+        deliveries, // GET      /api/webhooks/<webhook_id>/deliveries
+    ]
+}
@@ -0,0 +1,137 @@
+// Just enough of RFC 5545 to publish a read-only calendar of timed events
+
+pub struct Event {
+    pub uid: String,
+    pub stamp: i64,
+    pub start: i64,
+    pub end: i64,
+    pub summary: String,
+    pub description: String,
+}
+
+fn format_time(timestamp: i64) -> Option<String> {
+    chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|time| time.format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            ';' => result.push_str("\\;"),
+            ',' => result.push_str("\\,"),
+            '\n' => result.push_str("\\n"),
+            '\r' => (),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+// Lines longer than 75 octets continue on the next line after a single space
+fn push_line(output: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+        output.push(c);
+        length += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+pub fn calendar(name: &str, events: &[Event]) -> String {
+    let mut output = String::new();
+    push_line(&mut output, "BEGIN:VCALENDAR");
+    push_line(&mut output, "VERSION:2.0");
+    push_line(&mut output, "PRODID:-//Waitlist//Fleet Calendar//EN");
+    push_line(&mut output, "CALSCALE:GREGORIAN");
+    push_line(&mut output, &format!("X-WR-CALNAME:{}", escape(name)));
+
+    for event in events {
+        // An event we can't express is left out rather than breaking the whole calendar
+        let (stamp, start, end) = match (
+            format_time(event.stamp),
+            format_time(event.start),
+            format_time(event.end),
+        ) {
+            (Some(stamp), Some(start), Some(end)) => (stamp, start, end),
+            _ => continue,
+        };
+
+        push_line(&mut output, "BEGIN:VEVENT");
+        push_line(&mut output, &format!("UID:{}", escape(&event.uid)));
+        push_line(&mut output, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut output, &format!("DTSTART:{}", start));
+        push_line(&mut output, &format!("DTEND:{}", end));
+        push_line(&mut output, &format!("SUMMARY:{}", escape(&event.summary)));
+        if !event.description.is_empty() {
+            push_line(
+                &mut output,
+                &format!("DESCRIPTION:{}", escape(&event.description)),
+            );
+        }
+        push_line(&mut output, "END:VEVENT");
+    }
+
+    push_line(&mut output, "END:VCALENDAR");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a;b,c\\d\r\ne"), "a\\;b\\,c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_calendar() {
+        let output = calendar(
+            "Fleets",
+            &[Event {
+                uid: "planned-fleet-1@waitlist".to_string(),
+                stamp: 1700000000,
+                start: 1700003600,
+                end: 1700010800,
+                summary: "HQ fleet, shield".to_string(),
+                description: "x".repeat(100),
+            }],
+        );
+
+        assert!(output.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(output.ends_with("END:VCALENDAR\r\n"));
+        assert!(output.contains("\r\nDTSTART:20231114T231320Z\r\n"));
+        assert!(output.contains("\r\nSUMMARY:HQ fleet\\, shield\r\n"));
+        for line in output.split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert!(output.contains(&format!(
+            "DESCRIPTION:{}\r\n {}",
+            "x".repeat(63),
+            "x".repeat(37)
+        )));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let output = calendar(
+            "Fleets",
+            &[Event {
+                uid: "planned-fleet-1@waitlist".to_string(),
+                stamp: 1700000000,
+                start: i64::MIN,
+                end: 1700010800,
+                summary: "Broken".to_string(),
+                description: String::new(),
+            }],
+        );
+
+        assert!(!output.contains("BEGIN:VEVENT"));
+    }
+}
@@ -30,6 +30,8 @@ pub enum Madness {
     Forbidden(String),
     #[error("{0}")]
     NotFound(&'static str),
+    #[error("too many requests")]
+    TooManyRequests,
 }
 
 impl From<AuthorizationError> for Madness {
@@ -67,6 +69,7 @@ impl<'r> rocket::response::Responder<'r, 'static> for Madness {
 
             Self::NotFound(_) => Status::NotFound,
             Self::Forbidden(_) => Status::Forbidden,
+            Self::TooManyRequests => Status::TooManyRequests,
 
             Self::FitError(_) | Self::BadRequest(_) | Self::TypeError(_) => Status::BadRequest,
         };
@@ -1,2 +1,4 @@
+pub mod ical;
 pub mod madness;
+pub mod template;
 pub mod types;
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+// Fills in `{placeholder}` markers. Placeholders without a value are left untouched, so a typo
+// in a template shows up in the result rather than silently disappearing.
+pub fn render(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => match values.get(&after[..end]) {
+                Some(value) => {
+                    result.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    result.push('{');
+                    rest = after;
+                }
+            },
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_render() {
+        let mut values = HashMap::new();
+        values.insert("fc", "Some FC".to_string());
+        values.insert("comms", "Channel 1".to_string());
+
+        assert_eq!(
+            render("FC: {fc}, comms: {comms}", &values),
+            "FC: Some FC, comms: Channel 1"
+        );
+        assert_eq!(render("{fc}{fc}", &values), "Some FCSome FC");
+        assert_eq!(render("{unknown} {fc}", &values), "{unknown} Some FC");
+        assert_eq!(render("{{fc}}", &values), "{Some FC}");
+        assert_eq!(render("open {fc", &values), "open {fc");
+        assert_eq!(render("", &values), "");
+    }
+}
@@ -6,6 +6,7 @@ import { AuthContext } from "../contexts";
 import { AuthStart, AuthCallback, AuthLogout } from "../Pages/Auth";
 import { BadgeIndex, Guide, GuideIndex } from "../Pages/Guide";
 import { Fits } from "../Pages/Fits";
+import { Calendar } from "../Pages/FC/Calendar";
 import { FCMenu, GuideFC } from "../Pages/FC/Index";
 import { Fleet, FleetRegister } from "../Pages/FC/Fleet";
 import { FleetCompHistory } from "../Pages/FC/FleetCompHistory";
@@ -109,6 +110,9 @@ export function Routes() {
       <Route exact path="/fc/bans">
         <AuthenticatedRoute component={<BansPage />} access="bans-manage" />
       </Route>
+      <Route exact path="/fc/calendar">
+        <AuthenticatedRoute component={<Calendar />} access="fleet-view" />
+      </Route>
       <Route exact path="/fc/commanders">
         <AuthenticatedRoute component={<CommandersPage />} access="commanders-view" />
       </Route>
@@ -80,30 +80,29 @@ const AnnouncementBanner = () => {
 
   const [announcments, setAnnouncments] = React.useState([]);
   const [ignoreIds, setIgnoreIds] = React.useState([]);
-  const [pathname, setPathname] = React.useState(window.location.pathname);
+  const [location, setLocation] = React.useState(window.location);
 
   // Forces the UI to re-render when React-Router changes the page
   // this allows us to display/filter out announcements appropriately
   const history = useHistory();
   useEffect(() => {
-    return history.listen((location) => setPathname(location?.pathname));
+    return history.listen((location) => setLocation(location));
   }, [history]);
 
-  // Update announcements when a payload is received from the SSE server
-  const handleAnnouncment = (evt) => {
-    setAnnouncments(JSON.parse(evt.data) ?? []);
-  };
-
+  // Events only say which announcement changed, the list itself depends on who is looking
   useEffect(() => {
     if (!eventContext) {
       return;
     }
 
-    eventContext.addEventListener("announcment;new", handleAnnouncment);
-    eventContext.addEventListener("announcment;updated", handleAnnouncment);
+    const handleAnnouncment = async () => {
+      setAnnouncments(await apiCall(`/api/v2/announcements`, {}));
+    };
+
+    const events = ["announcment;live", "announcment;updated", "announcment;expired"];
+    events.forEach((name) => eventContext.addEventListener(name, handleAnnouncment));
     return () => {
-      eventContext.removeEventListener("announcment;new", handleAnnouncment);
-      eventContext.removeEventListener("announcment;updated", handleAnnouncment);
+      events.forEach((name) => eventContext.removeEventListener(name, handleAnnouncment));
     };
   }, [eventContext]);
 
@@ -141,7 +140,13 @@ const AnnouncementBanner = () => {
           return null;
         }
 
-        const display_on_pages = announcment?.pages ? JSON.parse(announcment.pages) : [];
+        // Hide it once it ends, even if the expiry event hasn't arrived yet
+        if (announcment.ends_at && announcment.ends_at * 1000 <= Date.now()) {
+          return null;
+        }
+
+        const display_on_pages = announcment.targets.pages;
+        const pathname = location?.pathname ?? "";
 
         // If the announcment has been limited to specific pages we need to check we are on an approved page
         // If we aren't, then we do not render the announcment.
@@ -160,6 +165,13 @@ const AnnouncementBanner = () => {
           if (!render) return null;
         }
 
+        // Waitlist targets only match pages that are about one of those waitlists
+        const waitlists = announcment.targets.waitlists;
+        if (waitlists.length > 0) {
+          const waitlistId = parseInt(new URLSearchParams(location?.search ?? "").get("wl"));
+          if (!waitlists.includes(waitlistId)) return null;
+        }
+
         const created_at = new Date(announcment.created_at * 1000);
 
         return (
@@ -9,6 +9,7 @@ import { Button } from "../../Components/Form";
 import { ToastContext } from "../../contexts";
 import { AddAnnouncement, UpdateAnnouncement } from "./announcements/Modals";
 import { usePageTitle } from "../../Util/title";
+import { formatDatetime } from "../../Util/time";
 
 const Header = styled.div`
   padding-bottom: 10px;
@@ -29,7 +30,7 @@ async function deleteAnnouncement(id) {
 }
 
 const AnnouncementsPage = () => {
-  const [announcements, updateData] = useApi(`/api/v2/announcements`);
+  const [announcements, updateData] = useApi(`/api/v2/announcements?all=true`);
   const [modalOpen, setModalOpen] = React.useState(false);
 
   const toastContext = React.useContext(ToastContext);
@@ -44,9 +45,30 @@ const AnnouncementsPage = () => {
     {
       name: "Pages",
       selector: (row) => {
-        const pages = row.pages;
-        if (!pages) return "All";
-        return JSON.parse(pages).join(", ");
+        const pages = row.targets.pages;
+        if (pages.length === 0) return "All";
+        return pages.join(", ");
+      },
+      wrap: true,
+    },
+    {
+      name: "Audience",
+      selector: (row) => {
+        const audience = [...row.targets.roles];
+        if (row.targets.waitlists.length > 0) {
+          audience.push(`waitlist ${row.targets.waitlists.join(", ")}`);
+        }
+        if (audience.length === 0) return "Everyone";
+        return audience.join(", ");
+      },
+      wrap: true,
+    },
+    {
+      name: "Shown",
+      selector: (row) => {
+        const start = formatDatetime(new Date(row.starts_at * 1000));
+        if (!row.ends_at) return `from ${start}`;
+        return `${start} - ${formatDatetime(new Date(row.ends_at * 1000))}`;
       },
       wrap: true,
     },
@@ -0,0 +1,299 @@
+import React from "react";
+import styled from "styled-components";
+import { AuthContext, ToastContext } from "../../contexts";
+import { apiCall, errorToaster, toaster, useApi } from "../../api";
+import { Box } from "../../Components/Box";
+import { CharacterName } from "../../Components/EntityLinks";
+import { AButton, Button, Buttons, Input, Label, Textarea } from "../../Components/Form";
+import { Confirm, Modal } from "../../Components/Modal";
+import { PageTitle, Title } from "../../Components/Page";
+import { Cell, CellHead, Row, Table, TableBody, TableHead } from "../../Components/Table";
+import { formatDatetime, fromInputTime, toInputTime } from "../../Util/time";
+import { usePageTitle } from "../../Util/title";
+
+const FormGroup = styled.div`
+  margin: 15px 0px;
+`;
+
+async function savePlannedFleet(id, fleet) {
+  return await apiCall(id ? `/api/calendar/${id}` : "/api/calendar", {
+    method: id ? "PUT" : "POST",
+    json: fleet,
+  });
+}
+
+async function deletePlannedFleet(id) {
+  return await apiCall(`/api/calendar/${id}`, {
+    method: "DELETE",
+  });
+}
+
+async function signUp(id, role) {
+  return await apiCall(`/api/calendar/${id}/signup`, {
+    json: { role },
+  });
+}
+
+async function withdraw(id) {
+  return await apiCall(`/api/calendar/${id}/signup`, {
+    method: "DELETE",
+  });
+}
+
+function PlannedFleetModal({ fleet, setOpen, onSaved }) {
+  const toastContext = React.useContext(ToastContext);
+  const [pending, setPending] = React.useState(false);
+  const [state, setState] = React.useState({
+    title: fleet?.title ?? "",
+    doctrine: fleet?.doctrine ?? "",
+    description: fleet?.description ?? "",
+    starts_at: toInputTime(fleet?.starts_at),
+    ends_at: toInputTime(fleet?.ends_at),
+    open_waitlist: fleet?.opens_waitlist ?? true,
+    empty_on_close: false,
+  });
+  const update = (key) => (evt) =>
+    setState({
+      ...state,
+      [key]: evt.target.type === "checkbox" ? evt.target.checked : evt.target.value,
+    });
+
+  const onSubmit = (evt) => {
+    evt.preventDefault();
+    setPending(true);
+    toaster(
+      toastContext,
+      savePlannedFleet(fleet?.id, {
+        ...state,
+        waitlist_id: fleet?.waitlist.id ?? 1,
+        description: state.description || null,
+        starts_at: fromInputTime(state.starts_at),
+        ends_at: fromInputTime(state.ends_at),
+      }).then((result) => {
+        setOpen(false);
+        onSaved();
+        return result;
+      })
+    ).finally(() => setPending(false));
+  };
+
+  return (
+    <Modal open={true} setOpen={setOpen}>
+      <Box>
+        <Title>{fleet ? "Edit planned fleet" : "Plan a fleet"}</Title>
+        <form onSubmit={onSubmit}>
+          <FormGroup>
+            <Label htmlFor="planned-title" required>
+              Title
+            </Label>
+            <Input id="planned-title" value={state.title} onChange={update("title")} required />
+          </FormGroup>
+          <FormGroup>
+            <Label htmlFor="planned-doctrine" required>
+              Doctrine
+            </Label>
+            <Input
+              id="planned-doctrine"
+              value={state.doctrine}
+              onChange={update("doctrine")}
+              required
+            />
+          </FormGroup>
+          <FormGroup>
+            <Label htmlFor="planned-starts" required>
+              Starts (EVE time)
+            </Label>
+            <Input
+              id="planned-starts"
+              type="datetime-local"
+              value={state.starts_at}
+              onChange={update("starts_at")}
+              required
+            />
+          </FormGroup>
+          <FormGroup>
+            <Label htmlFor="planned-ends" required>
+              Ends (EVE time)
+            </Label>
+            <Input
+              id="planned-ends"
+              type="datetime-local"
+              value={state.ends_at}
+              onChange={update("ends_at")}
+              required
+            />
+          </FormGroup>
+          <FormGroup>
+            <Label htmlFor="planned-description">Description</Label>
+            <Textarea
+              id="planned-description"
+              value={state.description}
+              onChange={update("description")}
+              style={{ width: "100%" }}
+            />
+          </FormGroup>
+          <FormGroup>
+            <label>
+              <input
+                type="checkbox"
+                checked={state.open_waitlist}
+                onChange={update("open_waitlist")}
+              />{" "}
+              Open the waitlist when the fleet starts and close it when it ends
+            </label>
+            {state.open_waitlist && (
+              <div>
+                <label>
+                  <input
+                    type="checkbox"
+                    checked={state.empty_on_close}
+                    onChange={update("empty_on_close")}
+                  />{" "}
+                  Empty the waitlist when it closes
+                </label>
+              </div>
+            )}
+          </FormGroup>
+          <Buttons>
+            <Button variant="success" type="submit" disabled={pending}>
+              Save
+            </Button>
+            <Button type="button" onClick={() => setOpen(false)}>
+              Cancel
+            </Button>
+          </Buttons>
+        </form>
+      </Box>
+    </Modal>
+  );
+}
+
+function SignupButtons({ fleet, onChange }) {
+  const authContext = React.useContext(AuthContext);
+  const toastContext = React.useContext(ToastContext);
+  const me = authContext.account_id;
+  const signedUp = fleet.fc?.id === me || fleet.backups.some((backup) => backup.id === me);
+
+  const run = (promise) => errorToaster(toastContext, promise.then(onChange));
+
+  if (signedUp) {
+    return <Button onClick={() => run(withdraw(fleet.id))}>Withdraw</Button>;
+  }
+
+  return (
+    <>
+      {!fleet.fc && (
+        <Button variant="success" onClick={() => run(signUp(fleet.id, "fc"))}>
+          FC
+        </Button>
+      )}
+      <Button onClick={() => run(signUp(fleet.id, "backup"))}>Backup</Button>
+    </>
+  );
+}
+
+export function Calendar() {
+  const authContext = React.useContext(AuthContext);
+  const toastContext = React.useContext(ToastContext);
+  const [fleets, refresh] = useApi("/api/calendar");
+  const [editing, setEditing] = React.useState(null);
+  const [deleting, setDeleting] = React.useState(null);
+  const canManage = authContext && authContext.access["fleet-configure"];
+
+  usePageTitle("Calendar");
+  return (
+    <>
+      <PageTitle>Fleet calendar</PageTitle>
+      <Buttons>
+        {canManage && (
+          <Button variant="primary" onClick={() => setEditing({})}>
+            Plan a fleet
+          </Button>
+        )}
+        <AButton href="/api/calendar.ics" title="Subscribe to this calendar in your calendar app">
+          iCal feed
+        </AButton>
+      </Buttons>
+
+      {!fleets ? (
+        <em>Loading calendar...</em>
+      ) : !fleets.length ? (
+        <em>No fleets planned.</em>
+      ) : (
+        <Table fullWidth>
+          <TableHead>
+            <Row>
+              <CellHead>Starts</CellHead>
+              <CellHead>Fleet</CellHead>
+              <CellHead>Doctrine</CellHead>
+              <CellHead>FC</CellHead>
+              <CellHead>Backup</CellHead>
+              {canManage && <CellHead></CellHead>}
+            </Row>
+          </TableHead>
+          <TableBody>
+            {fleets.map((fleet) => (
+              <Row key={fleet.id}>
+                <Cell>
+                  {formatDatetime(new Date(fleet.starts_at * 1000))}
+                  <br />
+                  <small>until {formatDatetime(new Date(fleet.ends_at * 1000))}</small>
+                </Cell>
+                <Cell title={fleet.description ?? ""}>
+                  {fleet.title}
+                  {fleet.opens_waitlist && (
+                    <>
+                      <br />
+                      <small>Opens {fleet.waitlist.name}</small>
+                    </>
+                  )}
+                </Cell>
+                <Cell>{fleet.doctrine}</Cell>
+                <Cell>{fleet.fc ? <CharacterName {...fleet.fc} /> : <em>TBD</em>}</Cell>
+                <Cell>
+                  {fleet.backups.map((backup) => (
+                    <div key={backup.id}>
+                      <CharacterName {...backup} />
+                    </div>
+                  ))}
+                </Cell>
+                {canManage && (
+                  <Cell>
+                    <Buttons>
+                      <SignupButtons fleet={fleet} onChange={refresh} />
+                      <Button onClick={() => setEditing(fleet)}>Edit</Button>
+                      <Button variant="danger" onClick={() => setDeleting(fleet)}>
+                        Delete
+                      </Button>
+                    </Buttons>
+                  </Cell>
+                )}
+              </Row>
+            ))}
+          </TableBody>
+        </Table>
+      )}
+
+      {editing && (
+        <PlannedFleetModal
+          fleet={editing.id ? editing : null}
+          setOpen={() => setEditing(null)}
+          onSaved={refresh}
+        />
+      )}
+      <Confirm
+        open={!!deleting}
+        setOpen={() => setDeleting(null)}
+        title={`Delete ${deleting?.title}`}
+        onConfirm={() =>
+          toaster(toastContext, deletePlannedFleet(deleting.id).then((result) => {
+            refresh();
+            return result;
+          })).finally(() => setDeleting(null))
+        }
+      >
+        Sign-ups for this fleet are removed too.
+      </Confirm>
+    </>
+  );
+}
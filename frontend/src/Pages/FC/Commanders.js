@@ -7,6 +7,7 @@ import { formatDatetime } from "../../Util/time";
 import { CharacterName } from "../../Components/EntityLinks";
 import { AddButton, FilterComponents, RevokeButton } from "./commanders/TableControls";
 import CommanderModal from "./commanders/CommanderModal";
+import { RoleHistoryButton } from "./commanders/RoleHistory";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import { faUserEdit } from "@fortawesome/free-solid-svg-icons";
 import { usePageTitle } from "../../Util/title";
@@ -129,15 +130,25 @@ const CommandersPage = () => {
       compact: true,
       grow: 1,
       minWidth: "46",
-      selector: (row) => authContext && authContext.access['commanders-manage'] && (
+      selector: (row) => (
         <Buttons>
-          <CommanderModal character={row.character} current={row.role} handleRefresh={refreshData}>
-            <IconBtn>
-              <FontAwesomeIcon fixedWidth icon={faUserEdit} />
-              <span>Update</span>
-            </IconBtn>
-          </CommanderModal>
-          <RevokeButton character={row.character} role={row.role} refreshData={refreshData} />
+          <RoleHistoryButton character={row.character} />
+          {authContext && authContext.access['commanders-manage'] && (
+            <>
+              <CommanderModal
+                character={row.character}
+                current={row.role}
+                expiresAt={row.expires_at}
+                handleRefresh={refreshData}
+              >
+                <IconBtn>
+                  <FontAwesomeIcon fixedWidth icon={faUserEdit} />
+                  <span>Update</span>
+                </IconBtn>
+              </CommanderModal>
+              <RevokeButton character={row.character} role={row.role} refreshData={refreshData} />
+            </>
+          )}
         </Buttons>
       ),
     },
@@ -1,6 +1,7 @@
 import React from "react";
 import { AuthContext, ToastContext } from "../../contexts";
-import { Confirm } from "../../Components/Modal";
+import { Confirm, Modal } from "../../Components/Modal";
+import { Box } from "../../Components/Box";
 import { Button, Buttons, InputGroup, NavButton, Select } from "../../Components/Form";
 import { Content, Title } from "../../Components/Page";
 import { apiCall, errorToaster, toaster, useApi } from "../../api";
@@ -25,10 +26,17 @@ async function emptyWaitlist(waitlistId) {
   });
 }
 
-async function closeFleet(characterId) {
-  return await apiCall("/api/fleet/close", {
+async function setupFleet(characterId) {
+  const assignments = await apiCall("/api/fleet/setup", {
     json: { character_id: characterId },
   });
+  return `Fleet set up with ${Object.keys(assignments).length} waitlist squads`;
+}
+
+async function closeFleet(characterId, options) {
+  return await apiCall("/api/fleet/close", {
+    json: { character_id: characterId, ...options },
+  });
 }
 
 export function Fleet() {
@@ -50,6 +58,12 @@ export function Fleet() {
     <>
       <Buttons>
         <NavButton to="/fc/fleet/register">Configure fleet</NavButton>
+        <Button
+          variant="primary"
+          onClick={() => toaster(toastContext, setupFleet(authContext.current.id))}
+        >
+          Set up fleet automatically
+        </Button>
         <NavButton to="/auth/start/fc">ESI re-auth as FC</NavButton>
         <InputGroup>
           <Button variant="success" onClick={() => toaster(toastContext, setWaitlistOpen(1, true))}>
@@ -61,14 +75,15 @@ export function Fleet() {
           <Button onClick={() => setEmptyWaitlistModalOpen(true)}>Empty waitlist</Button>
         </InputGroup>
         <Button variant="danger" onClick={() => setFleetCloseModalOpen(true)}>
-          Kick everyone from fleet
+          Kick from fleet
         </Button>
       </Buttons>
       <Content>
         <p>
           Make sure you re-auth via ESI, then create an in-game fleet with your comp. Click the
           &quot;Configure fleet&quot; button, and select the five squads that the tool will invite
-          people into. Then open the waitlist, allowing people to X up.
+          people into, or click &quot;Set up fleet automatically&quot; to create a squad for every
+          waitlist category. Then open the waitlist, allowing people to X up.
         </p>
         <p>
           To hand over the fleet, transfer the star (Boss role). Then the new FC should go via
@@ -89,18 +104,9 @@ export function Fleet() {
       )}
 
       <FleetMembers />
-      <Confirm
-        open={fleetCloseModalOpen}
-        setOpen={setFleetCloseModalOpen}
-        title="Kick everyone from fleet"
-        onConfirm={(evt) =>
-          toaster(toastContext, closeFleet(authContext.current.id)).finally(() =>
-            setFleetCloseModalOpen(false)
-          )
-        }
-      >
-        Are you sure?
-      </Confirm>
+      {fleetCloseModalOpen && (
+        <FleetCloseModal open={fleetCloseModalOpen} setOpen={setFleetCloseModalOpen} />
+      )}
       <Confirm
         open={emptyWaitlistModalOpen}
         setOpen={setEmptyWaitlistModalOpen}
@@ -115,6 +121,124 @@ export function Fleet() {
   );
 }
 
+const closeOutcomes = {
+  kept: "Kept",
+  would_kick: "Will be removed",
+  kicked: "Removed",
+  already_gone: "Already left",
+  failed: "Failed",
+};
+
+function FleetCloseModal({ open, setOpen }) {
+  const authContext = React.useContext(AuthContext);
+  const toastContext = React.useContext(ToastContext);
+  const [categories] = useApi("/api/categories");
+  const [keepFcs, setKeepFcs] = React.useState(true);
+  const [keepCategories, setKeepCategories] = React.useState([]);
+  const [keepCharacters, setKeepCharacters] = React.useState([]);
+  const [preview, setPreview] = React.useState(null);
+  const [pending, setPending] = React.useState(false);
+
+  const options = {
+    keep_fcs: keepFcs,
+    keep_categories: keepCategories,
+    keep_characters: keepCharacters,
+  };
+
+  const toggle = (list, setList, value) =>
+    setList(list.includes(value) ? list.filter((v) => v !== value) : [...list, value]);
+
+  const onPreview = () => {
+    setPending(true);
+    errorToaster(
+      toastContext,
+      closeFleet(authContext.current.id, { ...options, preview: true }).then(setPreview)
+    ).finally(() => setPending(false));
+  };
+
+  const onClose = () => {
+    setPending(true);
+    toaster(
+      toastContext,
+      closeFleet(authContext.current.id, options).then((response) => {
+        setPreview(response);
+        return response.message;
+      })
+    ).finally(() => setPending(false));
+  };
+
+  return (
+    <Modal open={open} setOpen={setOpen}>
+      <Box>
+        <Title>Kick members from fleet</Title>
+        <p>
+          <label>
+            <input type="checkbox" checked={keepFcs} onChange={() => setKeepFcs(!keepFcs)} /> Keep
+            FCs
+          </label>
+        </p>
+        {categories && (
+          <p>
+            Keep squads:{" "}
+            {categories.categories.map((category) => (
+              <label key={category.id} style={{ marginRight: "1em" }}>
+                <input
+                  type="checkbox"
+                  checked={keepCategories.includes(category.id)}
+                  onChange={() => toggle(keepCategories, setKeepCategories, category.id)}
+                />{" "}
+                {category.name}
+              </label>
+            ))}
+          </p>
+        )}
+        {preview && (
+          <>
+            <p>{preview.message}</p>
+            <Table fullWidth>
+              <TableHead>
+                <Row>
+                  <CellHead>Pilot</CellHead>
+                  <CellHead>Squad</CellHead>
+                  <CellHead>Outcome</CellHead>
+                  <CellHead>Keep</CellHead>
+                </Row>
+              </TableHead>
+              <TableBody>
+                {preview.members.map((member) => (
+                  <Row key={member.id}>
+                    <Cell>{member.name ?? member.id}</Cell>
+                    <Cell>{member.wl_category ?? "-"}</Cell>
+                    <Cell title={member.error}>{closeOutcomes[member.outcome]}</Cell>
+                    <Cell>
+                      <input
+                        type="checkbox"
+                        checked={keepCharacters.includes(member.id)}
+                        onChange={() => toggle(keepCharacters, setKeepCharacters, member.id)}
+                      />
+                    </Cell>
+                  </Row>
+                ))}
+              </TableBody>
+            </Table>
+          </>
+        )}
+        <InputGroup style={{ marginTop: "1em" }}>
+          <Button onClick={onPreview} disabled={pending}>
+            Preview
+          </Button>
+          <Button variant="danger" onClick={onClose} disabled={pending}>
+            Kick
+          </Button>
+          <Button variant="secondary" onClick={() => setOpen(false)}>
+            Close
+          </Button>
+        </InputGroup>
+      </Box>
+    </Modal>
+  );
+}
+
 async function registerFleet({ fleetInfo, categoryMatches, authContext }) {
   return await apiCall("/api/fleet/register", {
     json: {
@@ -15,6 +15,7 @@ import {
   faUserShield,
   faBullhorn,
   faBan,
+  faCalendarAlt,
 } from "@fortawesome/free-solid-svg-icons";
 import { replaceTitle, parseMarkdownTitle, usePageTitle } from "../../Util/title";
 
@@ -120,6 +121,9 @@ export function FCMenu() {
         {authContext && authContext.access["badges-manage"] && (
           <GuideCard slug="badges" name="Badges" icon={faShieldAlt} />
         )}
+        {authContext && authContext.access["fleet-view"] && (
+          <GuideCard slug="calendar" name="Fleet Calendar" icon={faCalendarAlt} />
+        )}
         {authContext && authContext.access["commanders-view"] && (
           <GuideCard slug="commanders" name="Commanders" icon={faUserShield} />
         )}
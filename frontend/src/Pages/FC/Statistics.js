@@ -190,6 +190,30 @@ function FleetTimeByHullMonth({ data }) {
   );
 }
 
+function BossTimeByRoleMonth({ data }) {
+  const series = separateDataLabels2D(data);
+  return (
+    <ThemedBar
+      data={{
+        labels: series.labels,
+        datasets: _.map(series.series, (numbers, label) => ({
+          label: label,
+          data: numbers.map((seconds) => Math.round((seconds || 0) / 3600)),
+        })),
+      }}
+      options={{
+        scales: { x: { stacked: true }, y: { stacked: true } },
+        plugins: {
+          title: {
+            display: true,
+            text: "Hours as fleet boss by role held at the time",
+          },
+        },
+      }}
+    />
+  );
+}
+
 function XByHullMonth({ data }) {
   const series = separateDataLabels2D(data);
   return (
@@ -341,6 +365,9 @@ export function Statistics() {
       <Graph>
         <TimeSpentByHull28d data={statsData.fleet_seconds_by_hull_28d} />
       </Graph>
+      <Graph>
+        <BossTimeByRoleMonth data={statsData.boss_seconds_by_role_by_month} />
+      </Graph>
     </Row>
   );
 }
@@ -2,7 +2,7 @@ import React, { useEffect } from "react";
 import styled from "styled-components";
 import { apiCall, errorToaster } from "../../../api";
 import { Box } from "../../../Components/Box";
-import { Button, Buttons, Label, Select, Textarea } from "../../../Components/Form";
+import { Button, Buttons, Input, Label, Select, Textarea } from "../../../Components/Form";
 import { Modal } from "../../../Components/Modal";
 import { Title } from "../../../Components/Page";
 import { ToastContext } from "../../../contexts";
@@ -82,17 +82,123 @@ const PageFilters = ({ idPrefix = "", selectedFilters, onChange }) => {
   );
 };
 
+const ROLE_OPTIONS = [
+  { name: "Trainees", value: "trainee" },
+  { name: "FCs", value: "fc" },
+  { name: "Council", value: "council" },
+];
+
+const RoleFilters = ({ idPrefix = "", selected, onChange }) => {
+  const toggle = (role) => {
+    onChange(selected.includes(role) ? selected.filter((r) => r !== role) : [...selected, role]);
+  };
+
+  return (
+    <FormGroup>
+      <Label>Only show to: (leave blank for everyone)</Label>
+
+      {ROLE_OPTIONS.map((option, key) => {
+        const id = `${idPrefix}role-${option.value}`;
+        return (
+          <label key={key} htmlFor={id} style={PAGE_CHECKBOX_STYLES}>
+            <input
+              id={id}
+              type="checkbox"
+              checked={selected.includes(option.value)}
+              onChange={() => toggle(option.value)}
+            />{" "}
+            {option.name}
+          </label>
+        );
+      })}
+    </FormGroup>
+  );
+};
+
+// Times are entered and shown in EVE time (UTC)
+const toInputTime = (timestamp) =>
+  timestamp ? new Date(timestamp * 1000).toISOString().slice(0, 16) : "";
+const fromInputTime = (value) => (value ? Math.floor(Date.parse(`${value}:00Z`) / 1000) : null);
+
+const parseWaitlists = (value) =>
+  value
+    .split(",")
+    .map((id) => parseInt(id.trim()))
+    .filter((id) => !isNaN(id));
+
+const TargetingFields = ({ idPrefix = "", state, setState }) => {
+  const update = (key) => (value) => setState({ ...state, [key]: value });
+
+  return (
+    <>
+      <RoleFilters idPrefix={idPrefix} selected={state.roles} onChange={update("roles")} />
+
+      <FormGroup>
+        <Label htmlFor={`${idPrefix}waitlists`}>Only on waitlists: (IDs, comma separated)</Label>
+        <Input
+          id={`${idPrefix}waitlists`}
+          value={state.waitlists}
+          placeholder="All waitlists"
+          onChange={(e) => update("waitlists")(e.target.value)}
+        />
+      </FormGroup>
+
+      <FormGroup>
+        <Label htmlFor={`${idPrefix}starts-at`}>Starts at: (EVE time, leave blank for now)</Label>
+        <Input
+          id={`${idPrefix}starts-at`}
+          type="datetime-local"
+          value={state.startsAt}
+          onChange={(e) => update("startsAt")(e.target.value)}
+        />
+        <Label htmlFor={`${idPrefix}ends-at`} style={{ marginTop: "10px" }}>
+          Ends at: (EVE time, leave blank to keep it until deleted)
+        </Label>
+        <Input
+          id={`${idPrefix}ends-at`}
+          type="datetime-local"
+          value={state.endsAt}
+          onChange={(e) => update("endsAt")(e.target.value)}
+        />
+      </FormGroup>
+    </>
+  );
+};
+
+const EMPTY_TARGETING = { roles: [], waitlists: "", startsAt: "", endsAt: "" };
+
+const targetingFromData = (data) => ({
+  roles: data?.targets?.roles ?? [],
+  waitlists: (data?.targets?.waitlists ?? []).join(", "),
+  startsAt: toInputTime(data?.starts_at),
+  endsAt: toInputTime(data?.ends_at),
+});
+
+const buildRequest = (content, alert, pageFilters, targeting) => ({
+  message: content,
+  is_alert: alert,
+  targets: {
+    pages: pageFilters,
+    roles: targeting.roles,
+    waitlists: parseWaitlists(targeting.waitlists),
+  },
+  starts_at: fromInputTime(targeting.startsAt),
+  ends_at: fromInputTime(targeting.endsAt),
+});
+
 const AddAnnouncement = ({ isOpen, setOpen, refreshFunction }) => {
   const toastContext = React.useContext(ToastContext);
 
   const [alert, setAlert] = React.useState(false);
   const [content, setContent] = React.useState(undefined);
   const [pageFilters, setPageFilters] = React.useState([]);
+  const [targeting, setTargeting] = React.useState(EMPTY_TARGETING);
   const [pending, isPending] = React.useState(false);
 
   const resetInputs = () => {
     setAlert(false);
     setContent(undefined);
+    setTargeting(EMPTY_TARGETING);
   };
 
   const onSubmit = (e) => {
@@ -103,20 +209,11 @@ const AddAnnouncement = ({ isOpen, setOpen, refreshFunction }) => {
     }
     isPending(true);
 
-    let pages = null;
-    if (pageFilters.length > 0) {
-      pages = JSON.stringify(pageFilters);
-    }
-
     errorToaster(
       toastContext,
       apiCall(`/api/v2/announcements`, {
         method: "POST",
-        json: {
-          message: content,
-          is_alert: alert,
-          pages,
-        },
+        json: buildRequest(content, alert, pageFilters, targeting),
       })
         .then(() => {
           setOpen(false);
@@ -172,6 +269,7 @@ const AddAnnouncement = ({ isOpen, setOpen, refreshFunction }) => {
           </FormGroup>
 
           <PageFilters onChange={(e) => setPageFilters(e)} />
+          <TargetingFields state={targeting} setState={setTargeting} />
 
           <FormGroup>
             <Label>or select a template</Label>
@@ -210,7 +308,8 @@ const UpdateAnnouncement = ({ data, refreshFunction }) => {
 
     const [content, setContent] = React.useState(undefined);
     const [alert, setAlert] = React.useState(false);
-    const [pageFilters, setPageFilters] = React.useState([]);
+    const [pageFilters, setPageFilters] = React.useState(data?.targets?.pages ?? []);
+    const [targeting, setTargeting] = React.useState(targetingFromData(data));
     const [pending, isPending] = React.useState(false);
 
     const onSubmit = (e) => {
@@ -221,20 +320,11 @@ const UpdateAnnouncement = ({ data, refreshFunction }) => {
       }
       isPending(true);
 
-      let pages = null;
-      if (pageFilters.length > 0) {
-        pages = JSON.stringify(pageFilters);
-      }
-
       errorToaster(
         toastContext,
         apiCall(`/api/v2/announcements/${data.id}`, {
           method: "put",
-          json: {
-            message: content,
-            is_alert: alert,
-            pages,
-          },
+          json: buildRequest(content, alert, pageFilters, targeting),
         })
           .then(() => {
             setOpen(false);
@@ -289,9 +379,10 @@ const UpdateAnnouncement = ({ data, refreshFunction }) => {
 
             <PageFilters
               idPrefix={data.id}
-              selectedFilters={data?.pages ? JSON.parse(data.pages) : []}
+              selectedFilters={data?.targets?.pages ?? []}
               onChange={(e) => setPageFilters(e)}
             />
+            <TargetingFields idPrefix={`${data.id}-`} state={targeting} setState={setTargeting} />
 
             <Buttons style={{ paddingLeft: "8px" }}>
               <Button variant="danger" type="submit" disabled={pending}>
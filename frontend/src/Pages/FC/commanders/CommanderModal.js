@@ -1,27 +1,37 @@
 import React, { useEffect } from "react";
 import styled from "styled-components";
-import { apiCall, errorToaster, toaster, useApi } from "../../../api";
+import { apiCall, toaster, useApi } from "../../../api";
 import { Box } from "../../../Components/Box";
 import { CharacterName } from "../../../Components/EntityLinks";
-import { Button, CenteredButtons, Label, Select } from "../../../Components/Form";
+import { Button, CenteredButtons, Input, Label, Select } from "../../../Components/Form";
 import { Modal } from "../../../Components/Modal";
 import { Title } from "../../../Components/Page";
 import { AuthContext, ToastContext } from "../../../contexts";
+import { fromInputTime, toInputTime } from "../../../Util/time";
 
 const FormGroup = styled.div`
   margin: 15px 0px;
 `;
 
-async function assignRole(character_id, role) {
+async function assignRole(character_id, role, expires_at) {
   return await apiCall(`/api/commanders`, {
     method: "POST",
     json: {
       character_id,
       role,
+      expires_at,
     },
   });
 }
 
+// Leaving out expires_at keeps the current expiry, null removes it
+async function changeRole(character_id, role, expires_at) {
+  return await apiCall(`/api/commanders/${character_id}`, {
+    method: "PATCH",
+    json: expires_at === undefined ? { role } : { role, expires_at },
+  });
+}
+
 async function revokeRole(character_id) {
   return await apiCall(`/api/commanders/${character_id}`, {
     method: "DELETE",
@@ -32,6 +42,7 @@ const CommanderModal = ({
   character,
   children = "ACL",
   currentRole,
+  expiresAt,
   isRevokeable,
   handleRefresh,
 }) => {
@@ -75,6 +86,7 @@ const CommanderModal = ({
           {...{
             character,
             currentRole: _current,
+            expiresAt,
             isOpen,
             setOpen,
             isRevokeable,
@@ -88,10 +100,20 @@ const CommanderModal = ({
 
 export default CommanderModal;
 
-const CmdrModal = ({ character, currentRole, isRevokeable, isOpen, handleRefresh, setOpen }) => {
+const CmdrModal = ({
+  character,
+  currentRole,
+  expiresAt,
+  isRevokeable,
+  isOpen,
+  handleRefresh,
+  setOpen,
+}) => {
   const [options] = useApi(`/api/commanders/roles`);
   const [pending, setPending] = React.useState(false);
   const [selectedOption, selectOption] = React.useState(undefined);
+  const [expiry, setExpiry] = React.useState(toInputTime(expiresAt));
+  const [expiryChanged, setExpiryChanged] = React.useState(false);
   const toastContext = React.useContext(ToastContext);
 
   const onSubmit = () => {
@@ -100,12 +122,16 @@ const CmdrModal = ({ character, currentRole, isRevokeable, isOpen, handleRefresh
     }
     setPending(true);
 
-    errorToaster(toastContext, revokeRole(character?.id))
+    // Change an existing role in place so the history records one change, not a revoke and a grant
+    const expires_at = fromInputTime(expiry);
+    const request = currentRole
+      ? changeRole(character?.id, selectedOption, expiryChanged ? expires_at : undefined)
+      : assignRole(character?.id, selectedOption, expires_at);
+
+    toaster(toastContext, request)
       .then(() => {
-        toaster(toastContext, assignRole(character?.id, selectedOption)).then(() => {
-          handleRefresh();
-          selectOption(undefined);
-        });
+        handleRefresh();
+        selectOption(undefined);
       })
       .finally(() => setPending(false));
   };
@@ -163,6 +189,20 @@ const CmdrModal = ({ character, currentRole, isRevokeable, isOpen, handleRefresh
           )}
         </FormGroup>
 
+        <FormGroup>
+          <Label htmlFor={`role-expiry-${character?.id}`}>Expires (EVE time, optional):</Label>
+          <Input
+            id={`role-expiry-${character?.id}`}
+            type="datetime-local"
+            value={expiry}
+            onChange={(e) => {
+              setExpiry(e.target.value);
+              setExpiryChanged(true);
+            }}
+            style={{ width: "100%" }}
+          />
+        </FormGroup>
+
         <CenteredButtons>
           <Button variant="success" onClick={onSubmit} disabled={!options || pending}>
             Confirm
@@ -0,0 +1,112 @@
+import React from "react";
+import { faHistory } from "@fortawesome/free-solid-svg-icons";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { useApi } from "../../../api";
+import { Box } from "../../../Components/Box";
+import { CharacterName } from "../../../Components/EntityLinks";
+import { Button } from "../../../Components/Form";
+import { Modal } from "../../../Components/Modal";
+import { Title } from "../../../Components/Page";
+import Spinner from "../../../Components/Spinner";
+import { Table, TableHead, TableBody, Row, Cell, CellHead } from "../../../Components/Table";
+import { formatDatetime } from "../../../Util/time";
+
+function describe(line) {
+  switch (line.action) {
+    case "grant":
+      return `Granted ${line.role}`;
+    case "change":
+      return `Changed ${line.previous_role} to ${line.role}`;
+    case "revoke":
+      return `Revoked ${line.role}`;
+    case "expire":
+      return `${line.role} expired`;
+    default:
+      return `${line.action} ${line.role}`;
+  }
+}
+
+export function RoleHistoryTable({ history }) {
+  if (!history) {
+    return <Spinner />;
+  }
+
+  if (!history.length) {
+    return <em style={{ fontSize: "smaller" }}>No role changes recorded.</em>;
+  }
+
+  return (
+    <Table fullWidth>
+      <TableHead>
+        <Row>
+          <CellHead>When</CellHead>
+          <CellHead>Change</CellHead>
+          <CellHead>By</CellHead>
+        </Row>
+      </TableHead>
+      <TableBody>
+        {history.map((line, i) => (
+          <Row key={i}>
+            <Cell>{formatDatetime(new Date(line.changed_at * 1000))}</Cell>
+            <Cell>
+              {describe(line)}
+              {line.expires_at && (
+                <small>
+                  {" "}
+                  (until {formatDatetime(new Date(line.expires_at * 1000))})
+                </small>
+              )}
+            </Cell>
+            <Cell>{line.changed_by ? <CharacterName {...line.changed_by} /> : <em>System</em>}</Cell>
+          </Row>
+        ))}
+      </TableBody>
+    </Table>
+  );
+}
+
+// Shown on the pilot page; hidden for pilots that never held a role
+export function RoleHistory({ characterId }) {
+  const [data] = useApi(characterId ? `/api/history/roles?character_id=${characterId}` : null);
+
+  if (!data?.history?.length) {
+    return null;
+  }
+
+  return (
+    <div style={{ marginBottom: "25px" }}>
+      <Title>Role history</Title>
+      <RoleHistoryTable history={data.history} />
+    </div>
+  );
+}
+
+export function RoleHistoryButton({ character }) {
+  const [isOpen, setOpen] = React.useState(false);
+
+  return (
+    <>
+      <Button title="Role history" onClick={() => setOpen(true)}>
+        <FontAwesomeIcon fixedWidth icon={faHistory} />
+      </Button>
+      {isOpen && (
+        <RoleHistoryModal character={character} isOpen={isOpen} setOpen={setOpen} />
+      )}
+    </>
+  );
+}
+
+function RoleHistoryModal({ character, isOpen, setOpen }) {
+  const [data] = useApi(`/api/history/roles?character_id=${character.id}`);
+
+  return (
+    <Modal open={isOpen} setOpen={setOpen}>
+      <Box>
+        <Title style={{ marginBottom: "10px" }}>
+          <CharacterName {...character} avatarSize={32} noLink />
+        </Title>
+        <RoleHistoryTable history={data?.history} />
+      </Box>
+    </Modal>
+  );
+}
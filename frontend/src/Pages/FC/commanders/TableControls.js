@@ -11,6 +11,7 @@ import { Title } from "../../../Components/Page";
 import PilotSearch from "../../../Components/PilotSearch";
 import { addToast } from "../../../Components/Toast";
 import { AuthContext, ToastContext } from "../../../contexts";
+import { fromInputTime } from "../../../Util/time";
 
 const FormGroup = styled.div`
   margin: 15px 0px;
@@ -39,6 +40,7 @@ const AddButton = ({ refreshData }) => {
 
   const AddCommanderModal = ({ isOpen, setOpen, refreshData }) => {
     const [role, setRole] = React.useState(undefined);
+    const [expiry, setExpiry] = React.useState("");
     const [options] = useApi(`/api/commanders/roles`);
     const [character_id, setCharacterId] = React.useState(undefined);
     const [_reset, resetSearch] = React.useState(0);
@@ -62,6 +64,7 @@ const AddButton = ({ refreshData }) => {
           json: {
             character_id,
             role,
+            expires_at: fromInputTime(expiry),
           },
         }).then(() => {
           refreshData();
@@ -69,6 +72,7 @@ const AddButton = ({ refreshData }) => {
       );
 
       setRole("");
+      setExpiry("");
       setCharacterId("");
       setOpen(false);
       resetSearch((prev) => prev + 1);
@@ -119,6 +123,17 @@ const AddButton = ({ refreshData }) => {
               </Select>
             </FormGroup>
 
+            <FormGroup>
+              <Label htmlFor="role-expiry">Expires (EVE time, optional):</Label>
+              <Input
+                id="role-expiry"
+                type="datetime-local"
+                value={expiry}
+                onChange={(e) => setExpiry(e.target.value)}
+                style={{ width: "100%" }}
+              />
+            </FormGroup>
+
             <Button variant="success">Confirm</Button>
           </form>
         </Box>
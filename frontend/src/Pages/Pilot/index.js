@@ -24,6 +24,7 @@ import _ from "lodash";
 import CommanderModal from "../FC/commanders/CommanderModal";
 import { AccountBannedBanner } from "../FC/bans/AccountBanned";
 import AltCharacters from "./AltCharacters";
+import { RoleHistory } from "../FC/commanders/RoleHistory";
 import { usePageTitle } from "../../Util/title";
 
 const FilterButtons = styled.span`
@@ -232,6 +233,8 @@ function PilotDisplay({ authContext }) {
           <ActivitySummary summary={fleetHistory && fleetHistory.summary} />
 
           <AltCharacters character={basicInfo?.id} />
+
+          <RoleHistory characterId={basicInfo?.id} />
         </Col>
       </Row>
     </>
@@ -377,6 +377,21 @@ function PilotInformation({ characterId, authContext, id }) {
   );
 }
 
+function QueueDisplay({ fit }) {
+  const { position, eta } = fit.queue;
+  var etaText = null;
+  if (eta) {
+    const minutes = Math.round((eta * 1000 - Date.now()) / 60000);
+    etaText = minutes > 0 ? `, ETA ~${minutes} min` : ", up any moment";
+  }
+  return (
+    <span title="Your place among approved x-ups in this category">
+      #{position} in {fit.category}
+      {etaText}
+    </span>
+  );
+}
+
 export function XCard({ entry, fit, onAction }) {
   const authContext = React.useContext(AuthContext);
   const toastContext = React.useContext(ToastContext);
@@ -501,6 +516,11 @@ export function XCard({ entry, fit, onAction }) {
             </a>
           )}
         </XCardDOM.Footer>
+        {fit.queue && fit.queue.position && (
+          <XCardDOM.Footer>
+            <QueueDisplay fit={fit} />
+          </XCardDOM.Footer>
+        )}
         {!is_alt && _.isFinite(fit.hours_in_fleet) && fit.hours_in_fleet < 1 && (
           <XCardDOM.Footer>
             <span>NEWBRO</span>
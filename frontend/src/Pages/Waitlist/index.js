@@ -50,32 +50,159 @@ async function removeEntry(id) {
   });
 }
 
+function applyFitDelta(fit, delta) {
+  switch (delta.type) {
+    case "fit_approved":
+      return { ...fit, approved: true };
+    case "fit_rejected":
+      return { ...fit, approved: false, review_comment: delta.review_comment };
+    case "fit_invited":
+      return { ...fit, invited_at: delta.invited_at };
+    default:
+      return fit;
+  }
+}
+
+// Applies a waitlist_delta event, or returns null if it doesn't follow on from what we have
+function applyDeltas(current, update, canManage) {
+  if (!current || !current.open || update.version !== current.version + 1) return null;
+
+  var entries = current.waitlist.map((entry) => ({ ...entry, fits: [...entry.fits] }));
+  for (const delta of update.deltas) {
+    if (delta.type === "fit_added") {
+      var entry = entries.find((e) => e.id === delta.entry.id);
+      if (!entry) {
+        entry = { ...delta.entry, fits: [], can_remove: canManage };
+        entries.push(entry);
+      }
+      entry.fits.push(delta.fit);
+    } else if (delta.type === "fit_removed") {
+      entries.forEach((e) => {
+        if (e.id === delta.entry_id) e.fits = e.fits.filter((fit) => fit.id !== delta.fit_id);
+      });
+    } else if (delta.type === "entry_removed") {
+      entries = entries.filter((e) => e.id !== delta.entry_id);
+    } else if (["fit_approved", "fit_rejected", "fit_invited"].includes(delta.type)) {
+      entries.forEach((e) => {
+        e.fits = e.fits.map((fit) => (fit.id === delta.fit_id ? applyFitDelta(fit, delta) : fit));
+      });
+    } else {
+      return null;
+    }
+  }
+
+  return { ...current, version: update.version, waitlist: entries };
+}
+
+// Fills in the details of one of our own x-ups, which the shared deltas may leave out
+function applyOwnFit(current, update) {
+  if (!current || !current.open || current.version < update.version) return null;
+
+  var found = false;
+  const entries = current.waitlist.map((entry) => {
+    if (entry.id !== update.entry.id) return entry;
+    const fits = entry.fits.map((fit) => {
+      if (fit.id !== update.fit.id) return fit;
+      found = true;
+      return { ...update.fit, queue: fit.queue || { position: null, eta: null } };
+    });
+    return { ...entry, character: update.entry.character, can_remove: true, fits };
+  });
+
+  return found ? { ...current, waitlist: entries } : null;
+}
+
+// Renumbers our own x-ups the way the backend does: approved fits that haven't been invited yet,
+// per category, in the order they came in. The ETA moves with the position, at the wait per place
+// the backend reported for the category.
+function requeue(entries, waitPerPlace) {
+  const counts = {};
+  const positions = {};
+  _.sortBy(entries, "id").forEach((entry) => {
+    _.sortBy(entry.fits, "id").forEach((fit) => {
+      if (!fit.approved || fit.invited_at) return;
+      counts[fit.category] = (counts[fit.category] || 0) + 1;
+      positions[fit.id] = counts[fit.category];
+    });
+  });
+
+  const now = Math.floor(Date.now() / 1000);
+  return entries.map((entry) => ({
+    ...entry,
+    fits: entry.fits.map((fit) => {
+      if (!fit.queue) return fit;
+      const position = positions[fit.id] || null;
+      const wait = waitPerPlace && waitPerPlace[fit.category];
+      const eta = position && wait ? now + position * wait : null;
+      return { ...fit, queue: { position, eta } };
+    }),
+  }));
+}
+
 function useWaitlist(waitlistId) {
+  const authContext = React.useContext(AuthContext);
   const eventContext = React.useContext(EventContext);
 
-  const [waitlistData, refreshFn] = useApi(
+  const [waitlistData, refreshFn, setWaitlistData] = useApi(
     waitlistId ? `/api/waitlist?waitlist_id=${waitlistId}` : null
   );
+  // Several events can arrive before the next render
+  const dataRef = React.useRef(waitlistData);
+  dataRef.current = waitlistData;
+
+  const canManage = !!(authContext && authContext.access["waitlist-manage"]);
 
   // Listen for events
   React.useEffect(() => {
     if (!eventContext) return;
 
     const [updateFn, clearUpdateFn] = coalesceCalls(refreshFn, 2000);
+    const setData = function (data) {
+      dataRef.current = data;
+      setWaitlistData(data);
+    };
+    const setUpdated = function (data) {
+      setData({ ...data, waitlist: requeue(data.waitlist, data.wait_per_place) });
+    };
     const handleEvent = function (event) {
       var data = JSON.parse(event.data);
-      if (data.waitlist_id === waitlistId) {
+      if (data.waitlist_id !== waitlistId) return;
+      if (dataRef.current && data.version <= dataRef.current.version) return;
+      updateFn();
+    };
+    const handleDelta = function (event) {
+      var data = JSON.parse(event.data);
+      if (data.waitlist_id !== waitlistId) return;
+      if (dataRef.current && data.version <= dataRef.current.version) return;
+      const updated = applyDeltas(dataRef.current, data, canManage);
+      if (updated) {
+        setUpdated(updated);
+      } else {
+        updateFn();
+      }
+    };
+    const handleOwnFit = function (event) {
+      var data = JSON.parse(event.data);
+      if (data.waitlist_id !== waitlistId) return;
+      const updated = applyOwnFit(dataRef.current, data);
+      if (updated) {
+        setUpdated(updated);
+      } else {
         updateFn();
       }
     };
     eventContext.addEventListener("waitlist_update", handleEvent);
+    eventContext.addEventListener("waitlist_delta", handleDelta);
+    eventContext.addEventListener("waitlist_own_fit", handleOwnFit);
     eventContext.addEventListener("open", updateFn);
     return function () {
       clearUpdateFn();
       eventContext.removeEventListener("waitlist_update", handleEvent);
+      eventContext.removeEventListener("waitlist_delta", handleDelta);
+      eventContext.removeEventListener("waitlist_own_fit", handleOwnFit);
       eventContext.removeEventListener("open", updateFn);
     };
-  }, [refreshFn, eventContext, waitlistId]);
+  }, [refreshFn, setWaitlistData, eventContext, waitlistId, canManage]);
 
   return [waitlistData, refreshFn];
 }
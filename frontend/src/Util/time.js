@@ -23,6 +23,15 @@ export function formatDatetime(dateObj) {
   return dateObj.toLocaleString("en-GB", { timeZone: "UTC" });
 }
 
+// Converts between unix timestamps and datetime-local input values, in EVE time (UTC)
+export function toInputTime(timestamp) {
+  return timestamp ? new Date(timestamp * 1000).toISOString().slice(0, 16) : "";
+}
+
+export function fromInputTime(value) {
+  return value ? Math.floor(Date.parse(`${value}:00Z`) / 1000) : null;
+}
+
 export function timeTillNow(dateTime) {
   // Build an output string with the appropriate prefix & suffix
   const BuildOutputStr = (number, unit, past = false) => {
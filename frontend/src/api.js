@@ -84,5 +84,5 @@ export function useApi(path) {
     refreshFunction();
   }, [refreshFunction]);
 
-  return [data, refreshFunction];
+  return [data, refreshFunction, setData];
 }